@@ -0,0 +1,167 @@
+use std::convert::TryInto;
+
+use gltf::accessor::DataType;
+
+/// Decodes one component, applying the glTF normalization rules when
+/// `normalized` is set.
+pub(crate) fn decode_component(data_type: DataType, normalized: bool, bytes: &[u8]) -> f32 {
+    match (data_type, normalized) {
+        (DataType::F32, _) => f32::from_le_bytes(bytes[..4].try_into().unwrap()),
+        (DataType::I8, false) => bytes[0] as i8 as f32,
+        (DataType::I8, true) => (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+        (DataType::U8, false) => bytes[0] as f32,
+        (DataType::U8, true) => bytes[0] as f32 / 255.0,
+        (DataType::I16, false) => i16::from_le_bytes(bytes[..2].try_into().unwrap()) as f32,
+        (DataType::I16, true) => {
+            (i16::from_le_bytes(bytes[..2].try_into().unwrap()) as f32 / 32767.0).max(-1.0)
+        }
+        (DataType::U16, false) => u16::from_le_bytes(bytes[..2].try_into().unwrap()) as f32,
+        (DataType::U16, true) => {
+            u16::from_le_bytes(bytes[..2].try_into().unwrap()) as f32 / 65535.0
+        }
+        (DataType::U32, false) => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f32,
+        (DataType::U32, true) => {
+            u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f32 / u32::MAX as f32
+        }
+    }
+}
+
+fn decode_elements(
+    data: &[u8],
+    stride: usize,
+    count: usize,
+    data_type: DataType,
+    normalized: bool,
+    components: usize,
+) -> Vec<f32> {
+    let size = data_type.size();
+    let mut values = Vec::with_capacity(count * components);
+    for i in 0..count {
+        for c in 0..components {
+            let start = i * stride + c * size;
+            values.push(decode_component(
+                data_type,
+                normalized,
+                &data[start..start + size],
+            ));
+        }
+    }
+    values
+}
+
+fn view_data<'s>(view: &gltf::buffer::View, buffers: &'s [gltf::buffer::Data]) -> &'s [u8] {
+    &buffers[view.buffer().index()][view.offset()..view.offset() + view.length()]
+}
+
+/// Reads any accessor as floats, `dimensions().multiplicity()` per element.
+///
+/// Unlike the typed readers of `gltf::mesh::Reader`, this accepts every
+/// component type, which is what quantized and instancing attributes need.
+/// Sparse substitutions are applied, and accessors without a buffer view read as zeros.
+pub(crate) fn read_f32(accessor: &gltf::Accessor, buffers: &[gltf::buffer::Data]) -> Vec<f32> {
+    let data_type = accessor.data_type();
+    let components = accessor.dimensions().multiplicity();
+    let element_size = data_type.size() * components;
+    let count = accessor.count();
+    let mut values = match accessor.view() {
+        Some(view) => {
+            let data = &view_data(&view, buffers)[accessor.offset()..];
+            decode_elements(
+                data,
+                view.stride().unwrap_or(element_size),
+                count,
+                data_type,
+                accessor.normalized(),
+                components,
+            )
+        }
+        None => vec![0.0; count * components],
+    };
+    if let Some(sparse) = accessor.sparse() {
+        let sparse_count = sparse.count() as usize;
+        let indices = sparse.indices();
+        let index_data = &view_data(&indices.view(), buffers)[indices.offset() as usize..];
+        let index_size = match indices.index_type() {
+            gltf::accessor::sparse::IndexType::U8 => 1,
+            gltf::accessor::sparse::IndexType::U16 => 2,
+            gltf::accessor::sparse::IndexType::U32 => 4,
+        };
+        let sparse_indices = index_data
+            .chunks_exact(index_size)
+            .take(sparse_count)
+            .map(|bytes| {
+                let mut index = [0u8; 4];
+                index[..index_size].copy_from_slice(bytes);
+                u32::from_le_bytes(index) as usize
+            });
+        let sparse_values = sparse.values();
+        let value_data =
+            &view_data(&sparse_values.view(), buffers)[sparse_values.offset() as usize..];
+        let substitutions = decode_elements(
+            value_data,
+            element_size,
+            sparse_count,
+            data_type,
+            accessor.normalized(),
+            components,
+        );
+        for (index, value) in sparse_indices.zip(substitutions.chunks_exact(components)) {
+            let start = index * components;
+            values[start..start + components].copy_from_slice(value);
+        }
+    }
+    values
+}
+
+/// Reads an accessor as `N` component vectors, see [`read_f32`].
+pub(crate) fn read_vectors<const N: usize>(
+    accessor: &gltf::Accessor,
+    buffers: &[gltf::buffer::Data],
+) -> Vec<[f32; N]> {
+    read_f32(accessor, buffers)
+        .chunks_exact(accessor.dimensions().multiplicity())
+        .map(|value| {
+            let mut vector = [0.0; N];
+            for (v, c) in vector.iter_mut().zip(value) {
+                *v = *c;
+            }
+            vector
+        })
+        .collect()
+}
+
+/// Copies the raw bytes of every element, each padded with zeros to
+/// `padded_size` bytes. `None` for sparse accessors and accessors without a
+/// buffer view, whose values only exist after decoding.
+pub(crate) fn read_raw_elements(
+    accessor: &gltf::Accessor,
+    buffers: &[gltf::buffer::Data],
+    padded_size: usize,
+) -> Option<Vec<u8>> {
+    if accessor.sparse().is_some() {
+        return None;
+    }
+    let view = accessor.view()?;
+    let element_size = accessor.data_type().size() * accessor.dimensions().multiplicity();
+    let stride = view.stride().unwrap_or(element_size);
+    let data = &view_data(&view, buffers)[accessor.offset()..];
+    let mut elements = vec![0u8; accessor.count() * padded_size];
+    for (i, element) in elements.chunks_exact_mut(padded_size).enumerate() {
+        element[..element_size].copy_from_slice(&data[i * stride..i * stride + element_size]);
+    }
+    Some(elements)
+}
+
+#[test]
+fn test_decode_elements() {
+    let data = [0x00, 0x80, 0xff, 0x7f, 0x01, 0x00, 0xaa, 0xaa];
+    // normalized i16 scalars with a stride of 4 bytes
+    assert_eq!(
+        decode_elements(&data[..6], 4, 2, DataType::I16, true, 1),
+        vec![-1.0, 1.0 / 32767.0]
+    );
+    assert_eq!(
+        decode_elements(&data[..4], 4, 1, DataType::U8, false, 4),
+        vec![0.0, 128.0, 255.0, 127.0]
+    );
+}
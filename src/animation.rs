@@ -0,0 +1,234 @@
+use crate::scene_graph::SceneGraph;
+
+pub use gltf::animation::Interpolation;
+
+/// The node property driven by a [`Channel`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+    /// Morph target weights of the node's mesh.
+    Weights,
+}
+
+/// A sampler bound to the property of one node.
+#[derive(Clone, Debug)]
+pub struct Channel {
+    pub node_index: usize,
+    pub property: Property,
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds, strictly increasing.
+    pub inputs: Vec<f32>,
+    /// Keyframe values, `components` floats each. Cubic spline keyframes hold
+    /// an in-tangent, a value and an out-tangent in that order.
+    pub outputs: Vec<f32>,
+    /// Number of floats per value: 3, 4, or the morph target count for weights.
+    pub components: usize,
+}
+
+impl Channel {
+    fn value(&self, keyframe: usize) -> &[f32] {
+        let stride = match self.interpolation {
+            Interpolation::CubicSpline => self.components * 3,
+            _ => self.components,
+        };
+        let offset = keyframe * stride
+            + match self.interpolation {
+                Interpolation::CubicSpline => self.components,
+                _ => 0,
+            };
+        &self.outputs[offset..offset + self.components]
+    }
+
+    fn in_tangent(&self, keyframe: usize) -> &[f32] {
+        let offset = keyframe * self.components * 3;
+        &self.outputs[offset..offset + self.components]
+    }
+
+    fn out_tangent(&self, keyframe: usize) -> &[f32] {
+        let offset = keyframe * self.components * 3 + self.components * 2;
+        &self.outputs[offset..offset + self.components]
+    }
+
+    /// Samples the channel at `time`, clamping to the first and last keyframe.
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        let last = self.inputs.len() - 1;
+        if time <= self.inputs[0] {
+            return self.value(0).to_vec();
+        }
+        if time >= self.inputs[last] {
+            return self.value(last).to_vec();
+        }
+        let next = self.inputs.partition_point(|t| *t <= time);
+        let previous = next - 1;
+        let dt = self.inputs[next] - self.inputs[previous];
+        let t = (time - self.inputs[previous]) / dt;
+
+        match self.interpolation {
+            Interpolation::Step => self.value(previous).to_vec(),
+            Interpolation::Linear => {
+                let a = self.value(previous);
+                let b = self.value(next);
+                if self.property == Property::Rotation {
+                    let a = glam::Quat::from_slice(a);
+                    let b = glam::Quat::from_slice(b);
+                    a.slerp(b, t).to_array().to_vec()
+                } else {
+                    a.iter().zip(b).map(|(a, b)| a + (b - a) * t).collect()
+                }
+            }
+            Interpolation::CubicSpline => {
+                let t2 = t * t;
+                let t3 = t2 * t;
+                let p0 = self.value(previous);
+                let m0 = self.out_tangent(previous);
+                let p1 = self.value(next);
+                let m1 = self.in_tangent(next);
+                let mut value = (0..self.components)
+                    .map(|i| {
+                        (2.0 * t3 - 3.0 * t2 + 1.0) * p0[i]
+                            + (t3 - 2.0 * t2 + t) * dt * m0[i]
+                            + (-2.0 * t3 + 3.0 * t2) * p1[i]
+                            + (t3 - t2) * dt * m1[i]
+                    })
+                    .collect::<Vec<_>>();
+                if self.property == Property::Rotation {
+                    value = glam::Quat::from_slice(&value)
+                        .normalize()
+                        .to_array()
+                        .to_vec();
+                }
+                value
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub name: Option<String>,
+    pub channels: Vec<Channel>,
+    /// Time of the last keyframe of any channel, in seconds.
+    pub duration: f32,
+}
+
+impl Animation {
+    /// Nodes whose translation, rotation or scale is driven by the animation.
+    pub fn transformed_nodes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.channels
+            .iter()
+            .filter(|c| c.property != Property::Weights)
+            .map(|c| c.node_index)
+    }
+
+    /// Writes the values of every channel at `time` into the scene graph.
+    pub(crate) fn apply(&self, time: f32, scene_graph: &mut SceneGraph) {
+        for channel in &self.channels {
+            let value = channel.sample(time);
+            let node = &mut scene_graph.nodes[channel.node_index];
+            match channel.property {
+                Property::Translation => {
+                    node.transform.translation = glam::Vec3::from_slice(&value)
+                }
+                Property::Rotation => node.transform.rotation = glam::Quat::from_slice(&value),
+                Property::Scale => node.transform.scale = glam::Vec3::from_slice(&value),
+                Property::Weights => node.weights = value,
+            }
+        }
+    }
+}
+
+pub(crate) fn load_animations(
+    gltf_animations: gltf::iter::Animations,
+    buffers: &[gltf::buffer::Data],
+) -> Vec<Animation> {
+    gltf_animations
+        .map(|animation| {
+            let channels = animation
+                .channels()
+                .filter_map(|channel| {
+                    let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+                    let inputs = reader.read_inputs()?.collect::<Vec<_>>();
+                    if inputs.is_empty() {
+                        return None;
+                    }
+                    let (property, outputs, components) = match reader.read_outputs()? {
+                        gltf::animation::util::ReadOutputs::Translations(iter) => {
+                            (Property::Translation, iter.flatten().collect::<Vec<_>>(), 3)
+                        }
+                        gltf::animation::util::ReadOutputs::Rotations(iter) => (
+                            Property::Rotation,
+                            iter.into_f32().flatten().collect::<Vec<_>>(),
+                            4,
+                        ),
+                        gltf::animation::util::ReadOutputs::Scales(iter) => {
+                            (Property::Scale, iter.flatten().collect::<Vec<_>>(), 3)
+                        }
+                        gltf::animation::util::ReadOutputs::MorphTargetWeights(iter) => {
+                            let outputs = iter.into_f32().collect::<Vec<_>>();
+                            let values_per_keyframe = match channel.sampler().interpolation() {
+                                Interpolation::CubicSpline => inputs.len() * 3,
+                                _ => inputs.len(),
+                            };
+                            let components = outputs.len() / values_per_keyframe;
+                            (Property::Weights, outputs, components)
+                        }
+                    };
+                    Some(Channel {
+                        node_index: channel.target().node().index(),
+                        property,
+                        interpolation: channel.sampler().interpolation(),
+                        inputs,
+                        outputs,
+                        components,
+                    })
+                })
+                .collect::<Vec<_>>();
+            let duration = channels
+                .iter()
+                .map(|c| *c.inputs.last().unwrap())
+                .fold(0.0, f32::max);
+            Animation {
+                name: animation.name().map(|s| s.to_owned()),
+                channels,
+                duration,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_channel_sample() {
+    let linear = Channel {
+        node_index: 0,
+        property: Property::Translation,
+        interpolation: Interpolation::Linear,
+        inputs: vec![0.0, 1.0, 3.0],
+        outputs: vec![0.0, 0.0, 0.0, 1.0, 2.0, 3.0, 3.0, 2.0, 1.0],
+        components: 3,
+    };
+    assert_eq!(linear.sample(-1.0), vec![0.0, 0.0, 0.0]);
+    assert_eq!(linear.sample(0.5), vec![0.5, 1.0, 1.5]);
+    assert_eq!(linear.sample(2.0), vec![2.0, 2.0, 2.0]);
+    assert_eq!(linear.sample(5.0), vec![3.0, 2.0, 1.0]);
+
+    let step = Channel {
+        interpolation: Interpolation::Step,
+        ..linear.clone()
+    };
+    assert_eq!(step.sample(0.99), vec![0.0, 0.0, 0.0]);
+    assert_eq!(step.sample(1.0), vec![1.0, 2.0, 3.0]);
+
+    // zero tangents make the spline ease in and out, passing the midpoint halfway
+    let cubic = Channel {
+        property: Property::Weights,
+        interpolation: Interpolation::CubicSpline,
+        inputs: vec![0.0, 2.0],
+        outputs: vec![0.0, 0.0, 0.0, 0.0, 4.0, 0.0],
+        components: 1,
+        ..linear
+    };
+    assert_eq!(cubic.sample(1.0), vec![2.0]);
+    assert!(cubic.sample(0.5)[0] < 1.0);
+}
@@ -0,0 +1,85 @@
+/// Axis pointing up in the source asset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpAxis {
+    Y,
+    Z,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Handedness {
+    Right,
+    Left,
+}
+
+/// The coordinate system an asset was actually authored in, converted to
+/// glTF's Y-up, right-handed meters on import, see
+/// [`LoadOptions::axis_conversion`](crate::LoadOptions).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisConversion {
+    pub up: UpAxis,
+    /// `Left` mirrors the Z axis after the up axis is converted.
+    pub handedness: Handedness,
+    /// Length of one source unit in meters, 0.01 for centimeters.
+    pub meters_per_unit: f32,
+}
+
+impl Default for AxisConversion {
+    fn default() -> Self {
+        Self {
+            up: UpAxis::Y,
+            handedness: Handedness::Right,
+            meters_per_unit: 1.0,
+        }
+    }
+}
+
+impl AxisConversion {
+    /// Maps source coordinates to glTF coordinates.
+    pub fn matrix(&self) -> glam::Mat4 {
+        let rotation = match self.up {
+            UpAxis::Y => glam::Mat4::IDENTITY,
+            // +Z becomes +Y and +Y becomes -Z
+            UpAxis::Z => glam::Mat4::from_rotation_x(-std::f32::consts::FRAC_PI_2),
+        };
+        let mirror = match self.handedness {
+            Handedness::Right => glam::Mat4::IDENTITY,
+            Handedness::Left => glam::Mat4::from_scale(glam::Vec3::new(1.0, 1.0, -1.0)),
+        };
+        glam::Mat4::from_scale(glam::Vec3::splat(self.meters_per_unit)) * mirror * rotation
+    }
+
+    /// Whether the conversion mirrors geometry, which reverses triangle winding.
+    pub fn flips_handedness(&self) -> bool {
+        self.handedness == Handedness::Left
+    }
+}
+
+/// Scale factor of a transform without shear or non-uniform scale.
+pub(crate) fn uniform_scale(transform: &glam::Mat4) -> f32 {
+    transform.transform_vector3(glam::Vec3::X).length()
+}
+
+#[test]
+fn test_axis_conversion() {
+    let conversion = AxisConversion {
+        up: UpAxis::Z,
+        handedness: Handedness::Right,
+        meters_per_unit: 0.01,
+    };
+    let up = conversion
+        .matrix()
+        .transform_vector3(glam::Vec3::new(0.0, 0.0, 100.0));
+    assert!(up.abs_diff_eq(glam::Vec3::Y, 1e-6));
+    let forward = conversion
+        .matrix()
+        .transform_vector3(glam::Vec3::new(0.0, 100.0, 0.0));
+    assert!(forward.abs_diff_eq(-glam::Vec3::Z, 1e-6));
+    assert!((uniform_scale(&conversion.matrix()) - 0.01).abs() < 1e-9);
+    assert!(conversion.matrix().determinant() > 0.0);
+    let mirrored = AxisConversion {
+        handedness: Handedness::Left,
+        ..conversion
+    };
+    assert!(mirrored.flips_handedness());
+    assert!(mirrored.matrix().determinant() < 0.0);
+}
@@ -0,0 +1,220 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::deform::DeformedMesh;
+use crate::{InstanceInfo, MeshInfo};
+
+/// Axis-aligned bounding box. Empty boxes have `min` above `max`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: glam::Vec3,
+    pub max: glam::Vec3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Aabb {
+    pub fn empty() -> Self {
+        Self {
+            min: glam::Vec3::splat(f32::INFINITY),
+            max: glam::Vec3::splat(f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_points<I: IntoIterator<Item = glam::Vec3>>(points: I) -> Self {
+        points.into_iter().fold(Self::empty(), |aabb, point| Self {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn center(&self) -> glam::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> glam::Vec3 {
+        self.max - self.min
+    }
+
+    pub fn corners(&self) -> [glam::Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            glam::Vec3::new(a.x, a.y, a.z),
+            glam::Vec3::new(b.x, a.y, a.z),
+            glam::Vec3::new(a.x, b.y, a.z),
+            glam::Vec3::new(b.x, b.y, a.z),
+            glam::Vec3::new(a.x, a.y, b.z),
+            glam::Vec3::new(b.x, a.y, b.z),
+            glam::Vec3::new(a.x, b.y, b.z),
+            glam::Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    /// The box around this box after `transform`.
+    pub fn transformed(&self, transform: &glam::Mat4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        Self::from_points(
+            self.corners()
+                .iter()
+                .map(|corner| transform.transform_point3(*corner)),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: glam::Vec3,
+    pub radius: f32,
+}
+
+/// std430 layout of an [`Aabb`], as stored in
+/// [`Scene::instance_bounds_buffer`](crate::Scene::instance_bounds_buffer).
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct GpuAabb {
+    pub min: [f32; 3],
+    pub _padding0: u32,
+    pub max: [f32; 3],
+    pub _padding1: u32,
+}
+
+impl From<&Aabb> for GpuAabb {
+    fn from(aabb: &Aabb) -> Self {
+        Self {
+            min: aabb.min.to_array(),
+            _padding0: 0,
+            max: aabb.max.to_array(),
+            _padding1: 0,
+        }
+    }
+}
+
+/// Bounds of a float accessor from its `min` and `max`, which glTF requires
+/// for positions. `None` for other component types, whose limits are stored
+/// before normalization.
+pub(crate) fn accessor_bounds(accessor: &gltf::Accessor) -> Option<Aabb> {
+    if accessor.data_type() != gltf::accessor::DataType::F32 {
+        return None;
+    }
+    let vector = |value: gltf::json::Value| match value.as_array()?.as_slice() {
+        [x, y, z] => Some(glam::Vec3::new(
+            x.as_f64()? as f32,
+            y.as_f64()? as f32,
+            z.as_f64()? as f32,
+        )),
+        _ => None,
+    };
+    Some(Aabb {
+        min: vector(accessor.min()?)?,
+        max: vector(accessor.max()?)?,
+    })
+}
+
+/// World space bounds of every instance, deformed meshes using their current
+/// pose.
+pub(crate) fn gather_instance_bounds(
+    instance_infos: &[InstanceInfo],
+    mesh_infos: &[MeshInfo],
+    deformed_meshes: &[DeformedMesh],
+    root_transform: &glam::Mat4,
+) -> Vec<Aabb> {
+    instance_infos
+        .iter()
+        .map(|info| instance_bounds(info, mesh_infos, deformed_meshes, root_transform))
+        .collect()
+}
+
+/// World space bounds of one instance, see [`gather_instance_bounds`].
+pub(crate) fn instance_bounds(
+    info: &InstanceInfo,
+    mesh_infos: &[MeshInfo],
+    deformed_meshes: &[DeformedMesh],
+    root_transform: &glam::Mat4,
+) -> Aabb {
+    let bounds = deformed_meshes
+        .iter()
+        .find(|d| Some(d.node_index) == info.node_index && d.mesh_index == info.mesh_index)
+        .map_or(mesh_infos[info.mesh_index].bounds, |d| d.bounds);
+    bounds.transformed(&(*root_transform * info.transform))
+}
+
+/// A sphere around `bounds`, centered on their combined box.
+pub(crate) fn bounding_sphere(bounds: &[Aabb]) -> BoundingSphere {
+    let center = bounds
+        .iter()
+        .fold(Aabb::empty(), |a, b| a.union(b))
+        .center();
+    let radius = bounds
+        .iter()
+        .filter(|b| !b.is_empty())
+        .flat_map(|b| b.corners())
+        .map(|corner| corner.distance(center))
+        .fold(0.0, f32::max);
+    BoundingSphere { center, radius }
+}
+
+/// Moves the center of `bounds` to the origin and scales their longest side
+/// to `extent`. Empty bounds are left alone and flat ones only moved.
+pub(crate) fn normalization(bounds: &[Aabb], extent: f32) -> glam::Mat4 {
+    let aabb = bounds.iter().fold(Aabb::empty(), |a, b| a.union(b));
+    if aabb.is_empty() {
+        return glam::Mat4::IDENTITY;
+    }
+    let longest_side = aabb.size().max_element();
+    let scale = match longest_side > 0.0 {
+        true => extent / longest_side,
+        false => 1.0,
+    };
+    glam::Mat4::from_scale(glam::Vec3::splat(scale)) * glam::Mat4::from_translation(-aabb.center())
+}
+
+#[test]
+fn test_aabb_transformed() {
+    let aabb = Aabb::from_points([glam::Vec3::ZERO, glam::Vec3::ONE]);
+    let rotated = aabb.transformed(&glam::Mat4::from_rotation_z(std::f32::consts::FRAC_PI_2));
+    assert!(rotated
+        .min
+        .abs_diff_eq(glam::Vec3::new(-1.0, 0.0, 0.0), 1e-6));
+    assert!(rotated
+        .max
+        .abs_diff_eq(glam::Vec3::new(0.0, 1.0, 1.0), 1e-6));
+    assert!(Aabb::empty().transformed(&glam::Mat4::IDENTITY).is_empty());
+    let sphere = bounding_sphere(&[aabb, Aabb::empty()]);
+    assert!(sphere.center.abs_diff_eq(glam::Vec3::splat(0.5), 1e-6));
+    assert!((sphere.radius - 0.75f32.sqrt()).abs() < 1e-6);
+}
+
+#[test]
+fn test_normalization() {
+    let bounds = [
+        Aabb::from_points([
+            glam::Vec3::new(10.0, 0.0, 0.0),
+            glam::Vec3::new(14.0, 1.0, 1.0),
+        ]),
+        Aabb::empty(),
+    ];
+    let normalized = bounds[0].transformed(&normalization(&bounds, 2.0));
+    assert!(normalized
+        .min
+        .abs_diff_eq(glam::Vec3::new(-1.0, -0.25, -0.25), 1e-6));
+    assert!(normalized
+        .max
+        .abs_diff_eq(glam::Vec3::new(1.0, 0.25, 0.25), 1e-6));
+    assert_eq!(normalization(&[Aabb::empty()], 2.0), glam::Mat4::IDENTITY);
+}
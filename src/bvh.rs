@@ -0,0 +1,306 @@
+//! CPU bounding volume hierarchies over the triangles of each mesh, for
+//! picking and other queries that should not need a GPU round trip.
+
+use crate::geometry::MeshGeometry;
+use crate::{Aabb, MeshInfo};
+
+const LEAF_SIZE: usize = 4;
+
+/// A triangle of a mesh, with vertex indices relative to the mesh's first
+/// vertex.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BvhTriangle {
+    pub primitive_index: u32,
+    pub triangle_index: u32,
+    pub indices: [u32; 3],
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    bounds: Aabb,
+    /// Index of the first child for interior nodes, of the first triangle
+    /// for leaves.
+    start: u32,
+    /// Number of triangles, 0 for interior nodes, whose two children are
+    /// adjacent.
+    count: u32,
+}
+
+/// Hit of a ray against one triangle, `t` along the ray's direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TriangleHit {
+    pub triangle: BvhTriangle,
+    pub t: f32,
+    /// Weights of the triangle's second and third vertex.
+    pub u: f32,
+    pub v: f32,
+}
+
+/// Hierarchy over the triangles of one mesh. Children always come after
+/// their parent, which lets [`refit`](Self::refit) run in one reverse pass.
+#[derive(Clone, Debug)]
+pub(crate) struct Bvh {
+    nodes: Vec<Node>,
+    triangles: Vec<BvhTriangle>,
+}
+
+fn triangle_points(positions: &[[f32; 3]], triangle: &BvhTriangle) -> [glam::Vec3; 3] {
+    triangle
+        .indices
+        .map(|i| glam::Vec3::from(positions[i as usize]))
+}
+
+fn triangle_bounds(positions: &[[f32; 3]], triangle: &BvhTriangle) -> Aabb {
+    Aabb::from_points(triangle_points(positions, triangle))
+}
+
+/// Möller-Trumbore intersection, hitting both faces.
+fn intersect_triangle(
+    [a, b, c]: [glam::Vec3; 3],
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+) -> Option<(f32, f32, f32)> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = direction.cross(ac);
+    let determinant = ab.dot(p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+    let inverse_determinant = 1.0 / determinant;
+    let ao = origin - a;
+    let u = ao.dot(p) * inverse_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = ao.cross(ab);
+    let v = direction.dot(q) * inverse_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some((ac.dot(q) * inverse_determinant, u, v))
+}
+
+/// Distance along the ray to where it enters `bounds`, `None` on a miss or
+/// past `max_t`.
+fn intersect_bounds(
+    bounds: &Aabb,
+    origin: glam::Vec3,
+    inverse_direction: glam::Vec3,
+    max_t: f32,
+) -> Option<f32> {
+    let t0 = (bounds.min - origin) * inverse_direction;
+    let t1 = (bounds.max - origin) * inverse_direction;
+    let near = t0.min(t1).max_element().max(0.0);
+    let far = t0.max(t1).min_element().min(max_t);
+    match near <= far {
+        true => Some(near),
+        false => None,
+    }
+}
+
+impl Bvh {
+    pub fn build(mut triangles: Vec<BvhTriangle>, positions: &[[f32; 3]]) -> Self {
+        let mut bvh = Self {
+            nodes: vec![Node {
+                bounds: Aabb::empty(),
+                start: 0,
+                count: triangles.len() as u32,
+            }],
+            triangles: Vec::new(),
+        };
+        bvh.subdivide(0, &mut triangles, 0, positions);
+        bvh.triangles = triangles;
+        bvh
+    }
+
+    /// Builds over the triangles of `mesh`, as put in its BLAS.
+    pub fn for_mesh(mesh: &MeshInfo, geometry: &MeshGeometry) -> Self {
+        let first_vertex = mesh.primitive_infos.first().map_or(0, |p| p.first_vertex);
+        let mut triangles = Vec::new();
+        for (primitive_index, primitive) in mesh.primitive_infos.iter().enumerate() {
+            let base = (primitive.first_vertex - first_vertex) as u32;
+            for (triangle_index, indices) in geometry
+                .primitive_indices(primitive)
+                .chunks_exact(3)
+                .enumerate()
+            {
+                triangles.push(BvhTriangle {
+                    primitive_index: primitive_index as u32,
+                    triangle_index: triangle_index as u32,
+                    indices: [base + indices[0], base + indices[1], base + indices[2]],
+                });
+            }
+        }
+        Self::build(triangles, &geometry.positions[first_vertex as usize..])
+    }
+
+    fn subdivide(
+        &mut self,
+        node_index: usize,
+        triangles: &mut [BvhTriangle],
+        offset: usize,
+        positions: &[[f32; 3]],
+    ) {
+        let bounds = triangles.iter().fold(Aabb::empty(), |b, t| {
+            b.union(&triangle_bounds(positions, t))
+        });
+        self.nodes[node_index] = Node {
+            bounds,
+            start: offset as u32,
+            count: triangles.len() as u32,
+        };
+        if triangles.len() <= LEAF_SIZE {
+            return;
+        }
+        let centroid = |t: &BvhTriangle| triangle_bounds(positions, t).center();
+        let centroid_bounds = Aabb::from_points(triangles.iter().map(centroid));
+        let size = centroid_bounds.size();
+        let axis = match (size.x >= size.y, size.x >= size.z, size.y >= size.z) {
+            (true, true, _) => 0,
+            (false, _, true) => 1,
+            _ => 2,
+        };
+        if size[axis] <= 0.0 {
+            return;
+        }
+        // median split along the longest axis of the centroids
+        let middle = triangles.len() / 2;
+        triangles.select_nth_unstable_by(middle, |a, b| {
+            centroid(a)[axis]
+                .partial_cmp(&centroid(b)[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let children = self.nodes.len();
+        self.nodes[node_index].start = children as u32;
+        self.nodes[node_index].count = 0;
+        self.nodes.extend([self.nodes[node_index]; 2]);
+        let (left, right) = triangles.split_at_mut(middle);
+        self.subdivide(children, left, offset, positions);
+        self.subdivide(children + 1, right, offset + middle, positions);
+    }
+
+    /// Recomputes the bounds of every node for moved vertices, keeping the
+    /// hierarchy.
+    pub fn refit(&mut self, positions: &[[f32; 3]]) {
+        for node_index in (0..self.nodes.len()).rev() {
+            let node = self.nodes[node_index];
+            let start = node.start as usize;
+            self.nodes[node_index].bounds = match node.count {
+                0 => self.nodes[start]
+                    .bounds
+                    .union(&self.nodes[start + 1].bounds),
+                count => self.triangles[start..start + count as usize]
+                    .iter()
+                    .fold(Aabb::empty(), |b, t| {
+                        b.union(&triangle_bounds(positions, t))
+                    }),
+            };
+        }
+    }
+
+    /// The closest triangle hit within `max_t`.
+    pub fn intersect(
+        &self,
+        positions: &[[f32; 3]],
+        origin: glam::Vec3,
+        direction: glam::Vec3,
+        max_t: f32,
+    ) -> Option<TriangleHit> {
+        let inverse_direction = direction.recip();
+        let mut closest: Option<TriangleHit> = None;
+        let mut stack = vec![0usize];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let limit = closest.map_or(max_t, |hit| hit.t);
+            if intersect_bounds(&node.bounds, origin, inverse_direction, limit).is_none() {
+                continue;
+            }
+            let start = node.start as usize;
+            if node.count == 0 {
+                stack.push(start);
+                stack.push(start + 1);
+                continue;
+            }
+            for triangle in &self.triangles[start..start + node.count as usize] {
+                let points = triangle_points(positions, triangle);
+                if let Some((t, u, v)) = intersect_triangle(points, origin, direction) {
+                    if t >= 0.0 && t < closest.map_or(max_t, |hit| hit.t) {
+                        closest = Some(TriangleHit {
+                            triangle: *triangle,
+                            t,
+                            u,
+                            v,
+                        });
+                    }
+                }
+            }
+        }
+        closest
+    }
+}
+
+/// The closest hit of [`Scene::raycast`](crate::Scene::raycast).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub instance_index: usize,
+    pub mesh_index: usize,
+    /// Index into the mesh's `primitive_infos`.
+    pub primitive_index: usize,
+    /// Index of the triangle within the primitive.
+    pub triangle_index: usize,
+    /// Weights of the triangle's three vertices.
+    pub barycentrics: glam::Vec3,
+    /// World space distance from the ray origin.
+    pub distance: f32,
+    pub position: glam::Vec3,
+    /// World space normal of the triangle's front face.
+    pub normal: glam::Vec3,
+    pub tex_coord: Option<glam::Vec2>,
+    pub color: Option<glam::Vec4>,
+}
+
+#[test]
+fn test_bvh_intersect() {
+    // a row of unit quads along +X at z = 0, two triangles each
+    let mut positions = Vec::new();
+    let mut triangles = Vec::new();
+    for i in 0..16u32 {
+        let x = i as f32;
+        positions.extend([
+            [x, 0.0, 0.0],
+            [x + 1.0, 0.0, 0.0],
+            [x, 1.0, 0.0],
+            [x + 1.0, 1.0, 0.0],
+        ]);
+        let base = i * 4;
+        for (triangle_index, indices) in [[0, 1, 2], [2, 1, 3]].iter().enumerate() {
+            triangles.push(BvhTriangle {
+                primitive_index: 0,
+                triangle_index: i * 2 + triangle_index as u32,
+                indices: indices.map(|j| base + j),
+            });
+        }
+    }
+    let mut bvh = Bvh::build(triangles, &positions);
+    let origin = glam::Vec3::new(9.25, 0.25, 2.0);
+    let hit = bvh
+        .intersect(&positions, origin, -glam::Vec3::Z, f32::INFINITY)
+        .unwrap();
+    assert_eq!(hit.triangle.triangle_index, 18);
+    assert!((hit.t - 2.0).abs() < 1e-6);
+    assert!((hit.u - 0.25).abs() < 1e-6 && (hit.v - 0.25).abs() < 1e-6);
+    assert!(bvh
+        .intersect(&positions, origin, -glam::Vec3::Z, 1.0)
+        .is_none());
+
+    // lowering the quads keeps the hierarchy valid after a refit
+    for p in &mut positions {
+        p[2] = -3.0;
+    }
+    bvh.refit(&positions);
+    let hit = bvh
+        .intersect(&positions, origin, -glam::Vec3::Z, f32::INFINITY)
+        .unwrap();
+    assert!((hit.t - 5.0).abs() < 1e-6);
+}
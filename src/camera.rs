@@ -0,0 +1,232 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::scene_graph::SceneGraph;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view in radians.
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        /// `None` for an infinite projection.
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+/// Clip space conventions of the projection matrices built by [`Camera`].
+///
+/// Depth always maps to `[0, 1]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClipConventions {
+    /// Map the near plane to depth 1 and the far plane to depth 0.
+    pub reversed_z: bool,
+    /// Flip Y so that +Y points down in clip space, as Vulkan expects.
+    pub y_down: bool,
+}
+
+impl Default for ClipConventions {
+    fn default() -> Self {
+        Self {
+            reversed_z: true,
+            y_down: true,
+        }
+    }
+}
+
+/// A camera placed in the scene by a node.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub name: Option<String>,
+    pub camera_index: usize,
+    pub node_index: usize,
+    pub projection: Projection,
+    /// World transform of the node carrying the camera. glTF cameras look
+    /// down their local -Z axis with +Y up.
+    pub world_transform: glam::Mat4,
+}
+
+impl Camera {
+    pub fn view(&self) -> glam::Mat4 {
+        self.world_transform.inverse()
+    }
+
+    pub fn inverse_view(&self) -> glam::Mat4 {
+        self.world_transform
+    }
+
+    pub fn position(&self) -> glam::Vec3 {
+        self.world_transform.transform_point3(glam::Vec3::ZERO)
+    }
+
+    pub fn forward(&self) -> glam::Vec3 {
+        self.world_transform
+            .transform_vector3(-glam::Vec3::Z)
+            .normalize()
+    }
+
+    /// The aspect ratio the camera asks for, falling back to `viewport_aspect_ratio`.
+    pub fn aspect_ratio(&self, viewport_aspect_ratio: f32) -> f32 {
+        match self.projection {
+            Projection::Perspective {
+                aspect_ratio: Some(aspect_ratio),
+                ..
+            } => aspect_ratio,
+            Projection::Orthographic { xmag, ymag, .. } => xmag / ymag,
+            _ => viewport_aspect_ratio,
+        }
+    }
+
+    pub fn projection(
+        &self,
+        viewport_aspect_ratio: f32,
+        conventions: ClipConventions,
+    ) -> glam::Mat4 {
+        let mut projection = match self.projection {
+            Projection::Perspective {
+                yfov, znear, zfar, ..
+            } => {
+                let aspect_ratio = self.aspect_ratio(viewport_aspect_ratio);
+                match (zfar, conventions.reversed_z) {
+                    (Some(zfar), false) => {
+                        glam::Mat4::perspective_rh(yfov, aspect_ratio, znear, zfar)
+                    }
+                    (Some(zfar), true) => {
+                        glam::Mat4::perspective_rh(yfov, aspect_ratio, zfar, znear)
+                    }
+                    (None, false) => glam::Mat4::perspective_infinite_rh(yfov, aspect_ratio, znear),
+                    (None, true) => {
+                        glam::Mat4::perspective_infinite_reverse_rh(yfov, aspect_ratio, znear)
+                    }
+                }
+            }
+            Projection::Orthographic {
+                xmag,
+                ymag,
+                znear,
+                zfar,
+            } => {
+                let (near, far) = match conventions.reversed_z {
+                    false => (znear, zfar),
+                    true => (zfar, znear),
+                };
+                glam::Mat4::orthographic_rh(-xmag, xmag, -ymag, ymag, near, far)
+            }
+        };
+        if conventions.y_down {
+            projection.y_axis.y = -projection.y_axis.y;
+        }
+        projection
+    }
+
+    pub fn inverse_projection(
+        &self,
+        viewport_aspect_ratio: f32,
+        conventions: ClipConventions,
+    ) -> glam::Mat4 {
+        self.projection(viewport_aspect_ratio, conventions)
+            .inverse()
+    }
+
+    /// Everything a ray generation shader needs to turn a pixel into a ray.
+    pub fn gpu_camera(
+        &self,
+        viewport_aspect_ratio: f32,
+        conventions: ClipConventions,
+    ) -> GpuCamera {
+        let aspect_ratio = self.aspect_ratio(viewport_aspect_ratio);
+        let (tan_half_fov, znear, zfar, orthographic) = match self.projection {
+            Projection::Perspective {
+                yfov, znear, zfar, ..
+            } => {
+                let tan_half_fov_y = (yfov * 0.5).tan();
+                (
+                    [tan_half_fov_y * aspect_ratio, tan_half_fov_y],
+                    znear,
+                    zfar.unwrap_or(f32::INFINITY),
+                    0,
+                )
+            }
+            Projection::Orthographic {
+                xmag,
+                ymag,
+                znear,
+                zfar,
+            } => ([xmag, ymag], znear, zfar, 1),
+        };
+        GpuCamera {
+            view: self.view().to_cols_array(),
+            inverse_view: self.inverse_view().to_cols_array(),
+            projection: self
+                .projection(viewport_aspect_ratio, conventions)
+                .to_cols_array(),
+            inverse_projection: self
+                .inverse_projection(viewport_aspect_ratio, conventions)
+                .to_cols_array(),
+            position: self.position().to_array(),
+            aspect_ratio,
+            tan_half_fov,
+            znear,
+            zfar,
+            orthographic,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// std140/std430 compatible camera block for ray generation shaders.
+///
+/// `tan_half_fov` holds the horizontal and vertical tangents of the half field
+/// of view, or the half extents for orthographic cameras.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct GpuCamera {
+    pub view: [f32; 16],
+    pub inverse_view: [f32; 16],
+    pub projection: [f32; 16],
+    pub inverse_projection: [f32; 16],
+    pub position: [f32; 3],
+    pub aspect_ratio: f32,
+    pub tan_half_fov: [f32; 2],
+    pub znear: f32,
+    pub zfar: f32,
+    pub orthographic: u32,
+    pub _padding: [u32; 3],
+}
+
+pub(crate) fn load_cameras(doc: &gltf::Document, scene_graph: &SceneGraph) -> Vec<Camera> {
+    scene_graph
+        .walk()
+        .into_iter()
+        .filter_map(|node_index| {
+            let camera = doc.nodes().nth(node_index)?.camera()?;
+            let projection = match camera.projection() {
+                gltf::camera::Projection::Perspective(p) => Projection::Perspective {
+                    yfov: p.yfov(),
+                    aspect_ratio: p.aspect_ratio(),
+                    znear: p.znear(),
+                    zfar: p.zfar(),
+                },
+                gltf::camera::Projection::Orthographic(o) => Projection::Orthographic {
+                    xmag: o.xmag(),
+                    ymag: o.ymag(),
+                    znear: o.znear(),
+                    zfar: o.zfar(),
+                },
+            };
+            Some(Camera {
+                name: camera.name().map(|s| s.to_owned()),
+                camera_index: camera.index(),
+                node_index,
+                projection,
+                world_transform: scene_graph.node(node_index).world_transform(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,195 @@
+use crate::bvh::Bvh;
+use crate::geometry::MeshGeometry;
+use crate::morph;
+use crate::scene_graph::SceneGraph;
+use crate::skin::{self, Skin};
+use crate::{Aabb, MeshData, MeshInfo};
+
+/// Per-node copy of a mesh whose vertices are deformed on the CPU, with the
+/// BLAS built from the deformed positions.
+#[derive(Clone)]
+pub(crate) struct DeformedMesh {
+    pub node_index: usize,
+    pub mesh_index: usize,
+    pub skin_index: Option<usize>,
+    /// Deformed positions of all primitives of the mesh, as tightly packed
+    /// `vec3`s in primitive order.
+    pub vertex_buffer: maligog::Buffer,
+    pub blas: maligog::BottomAccelerationStructure,
+    /// Built like `blas` once an instance of the node mirrors, see
+    /// [`create_blas`](crate::create_blas).
+    pub flipped_blas: Option<maligog::BottomAccelerationStructure>,
+    /// Object space bounds of the current pose.
+    pub bounds: Aabb,
+    /// CPU copy of `vertex_buffer`.
+    pub positions: Vec<[f32; 3]>,
+    /// Hierarchy over the deformed triangles, refit on every update.
+    pub bvh: Bvh,
+    /// Index of the first joint matrix of this mesh in the joint matrix buffer.
+    pub joint_offset: usize,
+}
+
+/// Range of the CPU geometry holding the positions of `mesh`, in vertices.
+pub(crate) fn mesh_vertex_range(mesh: &MeshInfo) -> std::ops::Range<usize> {
+    let start = mesh
+        .primitive_infos
+        .first()
+        .map_or(0, |p| p.first_vertex as usize);
+    let count = mesh
+        .primitive_infos
+        .iter()
+        .map(|p| p.vertex_count as usize)
+        .sum::<usize>();
+    start..start + count
+}
+
+fn create_vertex_buffer(device: &maligog::Device, positions: &[[f32; 3]]) -> maligog::Buffer {
+    device.create_buffer_init(
+        Some("deformed vertex buffer"),
+        bytemuck::cast_slice(positions),
+        maligog::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
+            | maligog::BufferUsageFlags::STORAGE_BUFFER,
+        maligog::MemoryLocation::CpuToGpu,
+    )
+}
+
+fn joint_matrices(
+    skins: &[Skin],
+    skin_index: Option<usize>,
+    scene_graph: &SceneGraph,
+    node_index: usize,
+) -> Vec<glam::Mat4> {
+    match skin_index {
+        Some(skin_index) => skins[skin_index].joint_matrices(scene_graph, node_index),
+        None => Vec::new(),
+    }
+}
+
+fn deformed_positions(
+    skin_index: Option<usize>,
+    mesh: &MeshInfo,
+    geometry: &MeshGeometry,
+    weights: &[f32],
+    joint_matrices: &[glam::Mat4],
+) -> Vec<[f32; 3]> {
+    let mut positions = Vec::with_capacity(mesh_vertex_range(mesh).len());
+    for (primitive, morph_targets) in mesh.primitive_infos.iter().zip(&mesh.morph_targets) {
+        // morph targets apply in bind space, before skinning
+        let base_positions = geometry.primitive_positions(primitive);
+        let morphed = morph::blend_positions(base_positions, morph_targets, weights);
+        let skinned = match (
+            skin_index,
+            geometry.primitive_joints(primitive),
+            geometry.primitive_weights(primitive),
+        ) {
+            (Some(_), Some(joints), Some(weights)) => {
+                skin::skin_positions(&morphed, joints, weights, joint_matrices)
+            }
+            _ => morphed,
+        };
+        positions.extend(skinned);
+    }
+    positions
+}
+
+/// Creates a copy of every skinned or morphed mesh of the loaded scene, posed
+/// as the scene graph is. Returns the joint matrices of all deformed meshes,
+/// concatenated.
+pub(crate) fn create_deformed_meshes(
+    device: &maligog::Device,
+    mesh_data: &MeshData,
+    skins: &[Skin],
+    scene_graph: &SceneGraph,
+) -> (Vec<DeformedMesh>, Vec<glam::Mat4>) {
+    let mut deformed_meshes = Vec::new();
+    let mut all_joint_matrices = Vec::new();
+    for node_index in scene_graph.walk() {
+        let node = scene_graph.node(node_index);
+        let mesh_index = match node.mesh() {
+            Some(mesh_index) => mesh_index,
+            None => continue,
+        };
+        let mesh = &mesh_data.mesh_infos[mesh_index];
+        if node.skin().is_none() && !mesh.has_morph_targets() {
+            continue;
+        }
+        let joint_matrices = joint_matrices(skins, node.skin(), scene_graph, node_index);
+        let positions = deformed_positions(
+            node.skin(),
+            mesh,
+            &mesh_data.geometry,
+            node.weights(),
+            &joint_matrices,
+        );
+        let vertex_buffer = create_vertex_buffer(device, &positions);
+        let blas = crate::create_blas(device, mesh_data, mesh, Some(&vertex_buffer), false);
+        let mut bvh = Bvh::for_mesh(mesh, &mesh_data.geometry);
+        bvh.refit(&positions);
+        deformed_meshes.push(DeformedMesh {
+            node_index,
+            mesh_index,
+            skin_index: node.skin(),
+            vertex_buffer,
+            blas,
+            flipped_blas: None,
+            bounds: Aabb::from_points(positions.iter().map(|p| glam::Vec3::from(*p))),
+            positions,
+            bvh,
+            joint_offset: all_joint_matrices.len(),
+        });
+        all_joint_matrices.extend(joint_matrices);
+    }
+    (deformed_meshes, all_joint_matrices)
+}
+
+/// Recomputes joint matrices and deformed positions from the current pose and
+/// morph target weights of the scene graph. Returns the joint matrices of all
+/// deformed meshes, concatenated, and whether the vertices of each deformed
+/// mesh moved.
+///
+/// maligog can't write into a buffer or rebuild a BLAS in place, so meshes
+/// whose vertices moved get a new vertex buffer and BLAS. The others keep
+/// theirs.
+pub(crate) fn update_deformed_meshes(
+    device: &maligog::Device,
+    deformed_meshes: &mut [DeformedMesh],
+    mesh_data: &MeshData,
+    skins: &[Skin],
+    scene_graph: &SceneGraph,
+) -> (Vec<glam::Mat4>, Vec<bool>) {
+    let mut all_joint_matrices = Vec::new();
+    let mut moved = Vec::with_capacity(deformed_meshes.len());
+    for deformed in deformed_meshes {
+        let joint_matrices =
+            joint_matrices(skins, deformed.skin_index, scene_graph, deformed.node_index);
+        let mesh = &mesh_data.mesh_infos[deformed.mesh_index];
+        let positions = deformed_positions(
+            deformed.skin_index,
+            mesh,
+            &mesh_data.geometry,
+            scene_graph.node(deformed.node_index).weights(),
+            &joint_matrices,
+        );
+        let changed = positions != deformed.positions;
+        moved.push(changed);
+        if changed {
+            deformed.vertex_buffer = create_vertex_buffer(device, &positions);
+            deformed.bounds = Aabb::from_points(positions.iter().map(|p| glam::Vec3::from(*p)));
+            deformed.bvh.refit(&positions);
+            deformed.positions = positions;
+            let vertex_buffer = Some(&deformed.vertex_buffer);
+            deformed.blas = crate::create_blas(device, mesh_data, mesh, vertex_buffer, false);
+            if deformed.flipped_blas.is_some() {
+                deformed.flipped_blas = Some(crate::create_blas(
+                    device,
+                    mesh_data,
+                    mesh,
+                    vertex_buffer,
+                    true,
+                ));
+            }
+        }
+        all_joint_matrices.extend(joint_matrices);
+    }
+    (all_joint_matrices, moved)
+}
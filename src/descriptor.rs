@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+
+use crate::Scene;
+
+/// Binding indices of the scene descriptor set, see [`Scene::descriptors`].
+///
+/// These are part of the public interface and will not be renumbered; new
+/// bindings are only ever appended.
+pub mod bindings {
+    /// `accelerationStructureEXT`, the scene TLAS.
+    pub const TLAS: u32 = 0;
+    /// `uint[]`, see [`Scene::index_buffer`](crate::Scene::index_buffer).
+    pub const INDEX_BUFFER: u32 = 1;
+    /// `float[]` holding tightly packed `vec3` positions.
+    pub const VERTEX_BUFFER: u32 = 2;
+    /// `vec4[]`, empty when no primitive has vertex colors.
+    pub const COLOR_BUFFER: u32 = 3;
+    /// `vec2[]`, empty when no primitive has texture coordinates.
+    pub const TEX_COORD_BUFFER: u32 = 4;
+    /// Row-major 3x4 instance transforms, in TLAS instance order.
+    pub const TRANSFORM_BUFFER: u32 = 5;
+    /// [`GpuMaterialInfo`](crate::GpuMaterialInfo)`[]`, indexed by `PrimitiveInfo::material_index`.
+    pub const MATERIAL_BUFFER: u32 = 6;
+    /// [`GpuPrimitiveInfo`](crate::GpuPrimitiveInfo)`[]` of every mesh, in mesh order.
+    pub const PRIMITIVE_BUFFER: u32 = 7;
+    /// `sampler[]`, indexed by `Texture::sampler_index`.
+    pub const SAMPLERS: u32 = 8;
+    /// `texture2D[]`, indexed by `Texture::image_index`.
+    pub const IMAGES: u32 = 9;
+    /// [`GpuGeometryInfo`](crate::GpuGeometryInfo)`[]`, indexed by
+    /// `gl_InstanceCustomIndexEXT + gl_GeometryIndexEXT`.
+    pub const GEOMETRY_BUFFER: u32 = 10;
+    /// [`GpuLight`](crate::GpuLight)`[]`, empty when the scene has no lights.
+    pub const LIGHT_BUFFER: u32 = 11;
+    /// [`GpuEmissiveTriangle`](crate::GpuEmissiveTriangle)`[]`, empty when
+    /// nothing in the scene emits light.
+    pub const EMISSIVE_TRIANGLE_BUFFER: u32 = 12;
+    /// `vec4[]`, see [`Scene::instance_attribute_buffer`](crate::Scene::instance_attribute_buffer).
+    pub const INSTANCE_ATTRIBUTE_BUFFER: u32 = 13;
+    /// `uvec2[]`, see [`Scene::quantized_vertex_buffer`](crate::Scene::quantized_vertex_buffer).
+    pub const QUANTIZED_VERTEX_BUFFER: u32 = 14;
+    /// [`GpuAabb`](crate::GpuAabb)`[]`, see [`Scene::instance_bounds_buffer`](crate::Scene::instance_bounds_buffer).
+    pub const INSTANCE_BOUNDS_BUFFER: u32 = 15;
+}
+
+/// Kind of the descriptors at a binding, see [`SceneResource::descriptor_type`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneDescriptorType {
+    AccelerationStructure,
+    StorageBuffer,
+    Sampler,
+    SampledImage,
+}
+
+/// What goes into one binding of the scene descriptor set.
+#[derive(Clone)]
+pub enum SceneResource {
+    AccelerationStructure(maligog::TopAccelerationStructure),
+    StorageBuffer(maligog::BufferView),
+    /// Indexed by `Texture::sampler_index`.
+    Samplers(Vec<maligog::Sampler>),
+    /// Indexed by `Texture::image_index`, may be empty.
+    Images(Vec<maligog::Image>),
+}
+
+impl SceneResource {
+    pub fn descriptor_type(&self) -> SceneDescriptorType {
+        match self {
+            SceneResource::AccelerationStructure(_) => SceneDescriptorType::AccelerationStructure,
+            SceneResource::StorageBuffer(_) => SceneDescriptorType::StorageBuffer,
+            SceneResource::Samplers(_) => SceneDescriptorType::Sampler,
+            SceneResource::Images(_) => SceneDescriptorType::SampledImage,
+        }
+    }
+
+    pub fn descriptor_count(&self) -> u32 {
+        match self {
+            SceneResource::AccelerationStructure(_) | SceneResource::StorageBuffer(_) => 1,
+            SceneResource::Samplers(samplers) => samplers.len() as u32,
+            SceneResource::Images(images) => images.len() as u32,
+        }
+    }
+}
+
+/// The resources of every binding in [`bindings`], from which renderers
+/// create the descriptor set layout, pool and set for the scene.
+#[derive(Clone)]
+pub struct SceneDescriptors {
+    resources: BTreeMap<u32, SceneResource>,
+}
+
+impl SceneDescriptors {
+    /// Resources by binding index.
+    pub fn resources(&self) -> &BTreeMap<u32, SceneResource> {
+        &self.resources
+    }
+
+    pub fn get(&self, binding: u32) -> Option<&SceneResource> {
+        self.resources.get(&binding)
+    }
+
+    /// Number of descriptors of type `ty` over all bindings, for sizing a
+    /// descriptor pool.
+    pub fn descriptor_count(&self, ty: SceneDescriptorType) -> u32 {
+        self.resources
+            .values()
+            .filter(|resource| resource.descriptor_type() == ty)
+            .map(SceneResource::descriptor_count)
+            .sum()
+    }
+}
+
+pub(crate) fn gather_scene_descriptors(
+    scene: &Scene,
+    empty_buffer: &maligog::Buffer,
+) -> SceneDescriptors {
+    let or_empty = |buffer_view: Option<maligog::BufferView>| {
+        buffer_view.unwrap_or_else(|| maligog::BufferView {
+            buffer: empty_buffer.clone(),
+            offset: 0,
+        })
+    };
+
+    let mut resources = BTreeMap::new();
+    resources.insert(
+        bindings::TLAS,
+        SceneResource::AccelerationStructure(scene.tlas().clone()),
+    );
+    for (binding, buffer_view) in [
+        (bindings::INDEX_BUFFER, scene.index_buffer()),
+        (bindings::VERTEX_BUFFER, scene.vertex_buffer()),
+        (bindings::COLOR_BUFFER, or_empty(scene.color_buffer())),
+        (
+            bindings::TEX_COORD_BUFFER,
+            or_empty(scene.tex_coord_buffer()),
+        ),
+        (bindings::TRANSFORM_BUFFER, scene.transform_buffer()),
+        (bindings::MATERIAL_BUFFER, scene.material_buffer()),
+        (bindings::PRIMITIVE_BUFFER, scene.primitive_buffer()),
+        (bindings::GEOMETRY_BUFFER, scene.geometry_buffer()),
+        (bindings::LIGHT_BUFFER, or_empty(scene.light_buffer())),
+        (
+            bindings::EMISSIVE_TRIANGLE_BUFFER,
+            or_empty(scene.emissive_triangle_buffer()),
+        ),
+        (
+            bindings::INSTANCE_ATTRIBUTE_BUFFER,
+            or_empty(scene.instance_attribute_buffer()),
+        ),
+        (
+            bindings::QUANTIZED_VERTEX_BUFFER,
+            or_empty(scene.quantized_vertex_buffer()),
+        ),
+        (
+            bindings::INSTANCE_BOUNDS_BUFFER,
+            or_empty(scene.instance_bounds_buffer()),
+        ),
+    ] {
+        resources.insert(binding, SceneResource::StorageBuffer(buffer_view));
+    }
+    resources.insert(
+        bindings::SAMPLERS,
+        SceneResource::Samplers(scene.samplers().to_vec()),
+    );
+    resources.insert(
+        bindings::IMAGES,
+        SceneResource::Images(scene.images().to_vec()),
+    );
+
+    SceneDescriptors { resources }
+}
@@ -0,0 +1,571 @@
+//! `KHR_draco_mesh_compression` primitives.
+//!
+//! The built-in decoder reads the subset of the Draco 2.2 bitstream that
+//! needs no entropy coding: sequentially encoded meshes with uncompressed
+//! indices and generic or quantized attributes without prediction. It does
+//! not implement edgebreaker connectivity, rANS coding or prediction
+//! schemes, which is what `draco_encoder` and glTF-Pipeline write by
+//! default. Such streams report [`DracoError::Unsupported`]; primitives that
+//! keep uncompressed fallback accessors are then read from those, the others
+//! need a [`DracoDecoder`] set in [`LoadOptions::draco_decoder`](crate::LoadOptions),
+//! for instance one wrapping the reference decoder.
+
+use std::convert::TryInto;
+
+use gltf::accessor::DataType;
+
+use crate::accessor;
+use crate::raw_json::RawJson;
+
+pub(crate) const EXTENSION_NAME: &str = "KHR_draco_mesh_compression";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DracoError {
+    /// Only version 2.2 streams are read.
+    UnsupportedVersion(u8, u8),
+    /// A valid stream using a feature the decoder lacks.
+    Unsupported(&'static str),
+    /// The stream ended early or contradicts itself.
+    Malformed,
+    /// The extension maps an attribute to a unique id the stream lacks.
+    MissingAttribute(String),
+}
+
+impl std::fmt::Display for DracoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DracoError::UnsupportedVersion(major, minor) => {
+                write!(f, "unsupported draco version {}.{}", major, minor)
+            }
+            DracoError::Unsupported(feature) => write!(f, "unsupported draco feature: {}", feature),
+            DracoError::Malformed => write!(f, "malformed draco stream"),
+            DracoError::MissingAttribute(name) => write!(f, "missing draco attribute {}", name),
+        }
+    }
+}
+
+impl std::error::Error for DracoError {}
+
+/// One decoded attribute, `components` values per point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DracoAttribute {
+    pub unique_id: u32,
+    pub components: usize,
+    /// Values as floats, normalized integers already mapped to `[0, 1]` or
+    /// `[-1, 1]`.
+    pub values: Vec<f32>,
+}
+
+/// A decoded Draco mesh.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DracoMesh {
+    /// Triangle list indexing points.
+    pub indices: Vec<u32>,
+    pub attributes: Vec<DracoAttribute>,
+}
+
+/// Decodes the compressed buffer view of a Draco primitive.
+pub trait DracoDecoder: Send + Sync {
+    fn decode(&self, data: &[u8]) -> Result<DracoMesh, DracoError>;
+}
+
+impl<F> DracoDecoder for F
+where
+    F: Fn(&[u8]) -> Result<DracoMesh, DracoError> + Send + Sync,
+{
+    fn decode(&self, data: &[u8]) -> Result<DracoMesh, DracoError> {
+        self(data)
+    }
+}
+
+/// The decoder used when [`LoadOptions::draco_decoder`](crate::LoadOptions)
+/// is unset, see the module documentation for what it reads.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SequentialDracoDecoder;
+
+impl DracoDecoder for SequentialDracoDecoder {
+    fn decode(&self, data: &[u8]) -> Result<DracoMesh, DracoError> {
+        decode_mesh(data)
+    }
+}
+
+const TRIANGULAR_MESH: u8 = 1;
+const MESH_SEQUENTIAL_ENCODING: u8 = 0;
+const MESH_EDGEBREAKER_ENCODING: u8 = 1;
+const METADATA_FLAG_MASK: u16 = 0x8000;
+const SEQUENTIAL_UNCOMPRESSED_INDICES: u8 = 1;
+const PREDICTION_NONE: i8 = -2;
+
+const DECODER_GENERIC: u8 = 0;
+const DECODER_INTEGER: u8 = 1;
+const DECODER_QUANTIZATION: u8 = 2;
+const DECODER_NORMALS: u8 = 3;
+
+/// Bounds checked reader over an encoded stream.
+struct Stream<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Stream<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], DracoError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or(DracoError::Malformed)?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, DracoError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Little endian unsigned integer of `size` bytes.
+    fn uint(&mut self, size: usize) -> Result<u32, DracoError> {
+        let mut value = [0u8; 4];
+        value[..size].copy_from_slice(self.bytes(size)?);
+        Ok(u32::from_le_bytes(value))
+    }
+
+    fn f32(&mut self) -> Result<f32, DracoError> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn varint(&mut self) -> Result<u32, DracoError> {
+        let mut result = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            result |= ((byte & 127) as u32) << shift;
+            if byte < 128 {
+                return Ok(result);
+            }
+        }
+        Err(DracoError::Malformed)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ComponentType {
+    Gltf(DataType),
+    I32,
+    Bool,
+}
+
+impl ComponentType {
+    fn from_u8(data_type: u8) -> Result<Self, DracoError> {
+        Ok(match data_type {
+            1 => ComponentType::Gltf(DataType::I8),
+            2 => ComponentType::Gltf(DataType::U8),
+            3 => ComponentType::Gltf(DataType::I16),
+            4 => ComponentType::Gltf(DataType::U16),
+            5 => ComponentType::I32,
+            6 => ComponentType::Gltf(DataType::U32),
+            7 | 8 | 10 => return Err(DracoError::Unsupported("64-bit attributes")),
+            9 => ComponentType::Gltf(DataType::F32),
+            11 => ComponentType::Bool,
+            _ => return Err(DracoError::Malformed),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ComponentType::Gltf(data_type) => data_type.size(),
+            ComponentType::I32 => 4,
+            ComponentType::Bool => 1,
+        }
+    }
+
+    fn decode_raw(self, normalized: bool, bytes: &[u8]) -> f32 {
+        match self {
+            ComponentType::Gltf(data_type) => {
+                accessor::decode_component(data_type, normalized, bytes)
+            }
+            ComponentType::I32 => self.int_value(
+                normalized,
+                i32::from_le_bytes(bytes[..4].try_into().unwrap()),
+            ),
+            ComponentType::Bool => (bytes[0] != 0) as u8 as f32,
+        }
+    }
+
+    /// Converts an integer decoded from a portable attribute.
+    fn int_value(self, normalized: bool, value: i32) -> f32 {
+        let (value, max) = match self {
+            ComponentType::Gltf(DataType::I8) => (value as f32, i8::MAX as f32),
+            ComponentType::Gltf(DataType::U8) => (value as f32, u8::MAX as f32),
+            ComponentType::Gltf(DataType::I16) => (value as f32, i16::MAX as f32),
+            ComponentType::Gltf(DataType::U16) => (value as f32, u16::MAX as f32),
+            ComponentType::Gltf(DataType::U32) => (value as u32 as f32, u32::MAX as f32),
+            ComponentType::I32 => (value as f32, i32::MAX as f32),
+            _ => (value as f32, 1.0),
+        };
+        match normalized {
+            true => (value / max).max(-1.0),
+            false => value,
+        }
+    }
+}
+
+/// Attribute values before dequantization.
+enum Portable {
+    Values(Vec<f32>),
+    Integers(Vec<i32>),
+}
+
+struct AttributeHeader {
+    unique_id: u32,
+    component_type: ComponentType,
+    components: usize,
+    normalized: bool,
+    decoder_type: u8,
+}
+
+fn unzigzag(v: u32) -> i32 {
+    ((v >> 1) ^ 0u32.wrapping_sub(v & 1)) as i32
+}
+
+fn decode_connectivity(stream: &mut Stream) -> Result<(Vec<u32>, usize), DracoError> {
+    let face_count = stream.varint()? as usize;
+    let point_count = stream.varint()? as usize;
+    if stream.byte()? != SEQUENTIAL_UNCOMPRESSED_INDICES {
+        return Err(DracoError::Unsupported("entropy coded indices"));
+    }
+    let index_count = face_count * 3;
+    let mut indices = Vec::with_capacity(index_count.min(stream.data.len()));
+    for _ in 0..index_count {
+        let index = match point_count {
+            c if c < 1 << 8 => stream.byte()? as u32,
+            c if c < 1 << 16 => stream.uint(2)?,
+            c if c < 1 << 21 => stream.varint()?,
+            _ => stream.uint(4)?,
+        };
+        if index as usize >= point_count {
+            return Err(DracoError::Malformed);
+        }
+        indices.push(index);
+    }
+    Ok((indices, point_count))
+}
+
+/// Reads the values of an integer or quantization coded attribute.
+fn decode_integer_values(stream: &mut Stream, count: usize) -> Result<Vec<i32>, DracoError> {
+    if stream.byte()? as i8 != PREDICTION_NONE {
+        return Err(DracoError::Unsupported("attribute prediction"));
+    }
+    if stream.byte()? != 0 {
+        return Err(DracoError::Unsupported("entropy coded attributes"));
+    }
+    let size = stream.byte()? as usize;
+    if !(1..=4).contains(&size) {
+        return Err(DracoError::Malformed);
+    }
+    (0..count)
+        .map(|_| Ok(unzigzag(stream.uint(size)?)))
+        .collect()
+}
+
+/// Decodes a Draco 2.2 mesh, see the module documentation for the subset
+/// that is supported.
+pub fn decode_mesh(data: &[u8]) -> Result<DracoMesh, DracoError> {
+    let mut stream = Stream { data, position: 0 };
+    if stream.bytes(5)? != b"DRACO" {
+        return Err(DracoError::Malformed);
+    }
+    let (major, minor) = (stream.byte()?, stream.byte()?);
+    if (major, minor) != (2, 2) {
+        return Err(DracoError::UnsupportedVersion(major, minor));
+    }
+    if stream.byte()? != TRIANGULAR_MESH {
+        return Err(DracoError::Unsupported("point clouds"));
+    }
+    match stream.byte()? {
+        MESH_SEQUENTIAL_ENCODING => {}
+        MESH_EDGEBREAKER_ENCODING => {
+            return Err(DracoError::Unsupported("edgebreaker connectivity"))
+        }
+        _ => return Err(DracoError::Malformed),
+    }
+    if stream.uint(2)? as u16 & METADATA_FLAG_MASK != 0 {
+        return Err(DracoError::Unsupported("metadata"));
+    }
+    let (indices, point_count) = decode_connectivity(&mut stream)?;
+
+    let mut decoders = Vec::new();
+    for _ in 0..stream.byte()? {
+        let mut headers = Vec::new();
+        for _ in 0..stream.varint()? {
+            let _attribute_type = stream.byte()?;
+            let component_type = ComponentType::from_u8(stream.byte()?)?;
+            let components = stream.byte()? as usize;
+            let normalized = stream.byte()? != 0;
+            let unique_id = stream.varint()?;
+            headers.push(AttributeHeader {
+                unique_id,
+                component_type,
+                components,
+                normalized,
+                decoder_type: 0,
+            });
+        }
+        for header in &mut headers {
+            header.decoder_type = stream.byte()?;
+        }
+        decoders.push(headers);
+    }
+
+    let mut attributes = Vec::new();
+    for headers in &decoders {
+        // every attribute's values come first, then the data needed to
+        // dequantize them
+        let mut portable = Vec::new();
+        for header in headers {
+            let count = point_count * header.components;
+            portable.push(match header.decoder_type {
+                DECODER_GENERIC => {
+                    let size = header.component_type.size();
+                    let values = stream
+                        .bytes(count * size)?
+                        .chunks_exact(size)
+                        .map(|bytes| header.component_type.decode_raw(header.normalized, bytes))
+                        .collect();
+                    Portable::Values(values)
+                }
+                DECODER_INTEGER | DECODER_QUANTIZATION => {
+                    Portable::Integers(decode_integer_values(&mut stream, count)?)
+                }
+                DECODER_NORMALS => return Err(DracoError::Unsupported("octahedral normals")),
+                _ => return Err(DracoError::Malformed),
+            });
+        }
+        for (header, values) in headers.iter().zip(portable) {
+            let values = match (header.decoder_type, values) {
+                (_, Portable::Values(values)) => values,
+                (DECODER_QUANTIZATION, Portable::Integers(values)) => {
+                    let min = (0..header.components)
+                        .map(|_| stream.f32())
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let range = stream.f32()?;
+                    let bits = stream.byte()?;
+                    if !(1..=30).contains(&bits) {
+                        return Err(DracoError::Malformed);
+                    }
+                    let delta = range / ((1u32 << bits) - 1) as f32;
+                    values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| min[i % header.components] + *v as f32 * delta)
+                        .collect()
+                }
+                (_, Portable::Integers(values)) => values
+                    .iter()
+                    .map(|v| header.component_type.int_value(header.normalized, *v))
+                    .collect(),
+            };
+            attributes.push(DracoAttribute {
+                unique_id: header.unique_id,
+                components: header.components,
+                values,
+            });
+        }
+    }
+    Ok(DracoMesh {
+        indices,
+        attributes,
+    })
+}
+
+/// A decoded primitive with its attributes named by glTF semantic.
+pub(crate) struct DracoPrimitive {
+    pub indices: Vec<u32>,
+    attributes: Vec<(String, DracoAttribute)>,
+}
+
+impl DracoPrimitive {
+    /// Values of the attribute with `semantic`, padded or truncated to `N`
+    /// components, with `fill` for missing components.
+    pub fn vectors<const N: usize>(&self, semantic: &str, fill: f32) -> Option<Vec<[f32; N]>> {
+        let (_, attribute) = self.attributes.iter().find(|(s, _)| s == semantic)?;
+        Some(
+            attribute
+                .values
+                .chunks_exact(attribute.components.max(1))
+                .map(|value| {
+                    let mut vector = [fill; N];
+                    for (v, c) in vector.iter_mut().zip(value) {
+                        *v = *c;
+                    }
+                    vector
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Indices of the accessors of Draco compressed primitives. The extension
+/// lets them omit their buffer view.
+pub(crate) fn compressed_accessors(doc: &gltf::Document, raw_json: &RawJson) -> Vec<usize> {
+    let mut accessors = Vec::new();
+    for mesh in doc.meshes() {
+        for primitive in mesh.primitives() {
+            if raw_json
+                .primitive_extension(mesh.index(), primitive.index(), EXTENSION_NAME)
+                .is_some()
+            {
+                accessors.extend(primitive.indices().map(|a| a.index()));
+                accessors.extend(primitive.attributes().map(|(_, a)| a.index()));
+            }
+        }
+    }
+    accessors
+}
+
+/// Whether the accessors of `primitive` point at data, which the extension
+/// allows as a fallback for loaders without Draco support.
+fn has_fallback(primitive: &gltf::Primitive) -> bool {
+    primitive
+        .indices()
+        .into_iter()
+        .chain(primitive.attributes().map(|(_, a)| a))
+        .all(|a| a.view().is_some())
+}
+
+/// Decodes `primitive` if it is Draco compressed, mapping the stream's
+/// attributes to semantics through the extension's attribute ids. Returns
+/// `None` for primitives to read from their accessors, including compressed
+/// ones with a fallback the decoder can't read.
+pub(crate) fn decode_primitive(
+    primitive: &gltf::Primitive,
+    mesh_index: usize,
+    raw_json: &RawJson,
+    buffers: &[gltf::buffer::Data],
+    doc: &gltf::Document,
+    decoder: &dyn DracoDecoder,
+) -> Result<Option<DracoPrimitive>, DracoError> {
+    let extension =
+        match raw_json.primitive_extension(mesh_index, primitive.index(), EXTENSION_NAME) {
+            Some(extension) => extension,
+            None => return Ok(None),
+        };
+    let view = extension
+        .get("bufferView")
+        .and_then(|v| v.as_u64())
+        .and_then(|v| doc.views().nth(v as usize))
+        .ok_or(DracoError::Malformed)?;
+    let data = buffers
+        .get(view.buffer().index())
+        .and_then(|b| b.get(view.offset()..view.offset() + view.length()))
+        .ok_or(DracoError::Malformed)?;
+    let mut mesh = match decoder.decode(data) {
+        Ok(mesh) => mesh,
+        Err(DracoError::Unsupported(feature)) if has_fallback(primitive) => {
+            log::warn!(
+                "reading the uncompressed fallback of a draco primitive of mesh {}: {}",
+                mesh_index,
+                feature
+            );
+            return Ok(None);
+        }
+        Err(error) => return Err(error),
+    };
+    let mut attributes = Vec::new();
+    if let Some(ids) = extension.get("attributes").and_then(|a| a.as_object()) {
+        for (semantic, id) in ids {
+            let id = id.as_u64().ok_or(DracoError::Malformed)? as u32;
+            let position = mesh
+                .attributes
+                .iter()
+                .position(|a| a.unique_id == id)
+                .ok_or_else(|| DracoError::MissingAttribute(semantic.clone()))?;
+            attributes.push((semantic.clone(), mesh.attributes.swap_remove(position)));
+        }
+    }
+    Ok(Some(DracoPrimitive {
+        indices: mesh.indices,
+        attributes,
+    }))
+}
+
+#[test]
+fn test_decode_sequential_mesh() {
+    let mut data = b"DRACO".to_vec();
+    // version 2.2, triangular mesh, sequential encoding, no flags
+    data.extend_from_slice(&[2, 2, 1, 0, 0, 0]);
+    // one face over three points with uncompressed byte indices
+    data.extend_from_slice(&[1, 3, 1, 0, 2, 1]);
+    // one attributes decoder with a float position and a quantized texcoord
+    data.extend_from_slice(&[1, 2, 0, 9, 3, 0, 0, 3, 9, 2, 0, 7, 0, 2]);
+    for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    // texcoords without prediction as zigzag coded bytes: (0, 0) (3, 0) (0, 3)
+    data.extend_from_slice(&[0xfe, 0, 1, 0, 0, 6, 0, 0, 6]);
+    for v in [0.0f32, 0.0, 1.0] {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    data.push(2);
+    let mesh = decode_mesh(&data).unwrap();
+    assert_eq!(mesh.indices, vec![0, 2, 1]);
+    assert_eq!(mesh.attributes[0].unique_id, 0);
+    assert_eq!(mesh.attributes[0].values[3], 1.0);
+    assert_eq!(mesh.attributes[1].unique_id, 7);
+    assert_eq!(
+        mesh.attributes[1].values,
+        vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0]
+    );
+
+    data[8] = MESH_EDGEBREAKER_ENCODING;
+    assert_eq!(
+        decode_mesh(&data),
+        Err(DracoError::Unsupported("edgebreaker connectivity"))
+    );
+}
+
+#[test]
+fn test_decode_primitive_fallback() {
+    let json = |position_view: &str| {
+        format!(
+            r#"{{
+                "asset": {{"version": "2.0"}},
+                "extensionsUsed": ["KHR_draco_mesh_compression"],
+                "buffers": [{{"byteLength": 48}}],
+                "bufferViews": [
+                    {{"buffer": 0, "byteLength": 36}},
+                    {{"buffer": 0, "byteOffset": 36, "byteLength": 12}}
+                ],
+                "accessors": [{{
+                    {}
+                    "componentType": 5126,
+                    "count": 3,
+                    "type": "VEC3",
+                    "min": [0, 0, 0],
+                    "max": [1, 1, 0]
+                }}],
+                "meshes": [{{"primitives": [{{
+                    "attributes": {{"POSITION": 0}},
+                    "extensions": {{"KHR_draco_mesh_compression": {{
+                        "bufferView": 1,
+                        "attributes": {{"POSITION": 0}}
+                    }}}}
+                }}]}}]
+            }}"#,
+            position_view
+        )
+    };
+    let buffers = vec![gltf::buffer::Data(vec![0; 48])];
+    let decoder = |_: &[u8]| Err(DracoError::Unsupported("edgebreaker connectivity"));
+    let decode = |json: String| {
+        let doc = gltf::Gltf::from_slice_without_validation(json.as_bytes())
+            .unwrap()
+            .document;
+        let raw_json = RawJson::from_slice(json.as_bytes()).unwrap();
+        crate::import::validate(&doc, &raw_json).unwrap();
+        let primitive = doc.meshes().next().unwrap().primitives().next().unwrap();
+        decode_primitive(&primitive, 0, &raw_json, &buffers, &doc, &decoder).map(|p| p.is_some())
+    };
+    assert_eq!(decode(json(r#""bufferView": 0,"#)), Ok(false));
+    assert_eq!(
+        decode(json("")),
+        Err(DracoError::Unsupported("edgebreaker connectivity"))
+    );
+}
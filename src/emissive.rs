@@ -0,0 +1,242 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::geometry::MeshGeometry;
+use crate::{InstanceInfo, MaterialInfo, MeshInfo};
+
+/// A triangle of an instance whose material emits light.
+#[derive(Clone, Copy, Debug)]
+pub struct EmissiveTriangle {
+    pub instance_index: usize,
+    /// Index into the geometry buffer, `custom_index + primitive index`.
+    pub geometry_index: u32,
+    /// Index of the triangle within its primitive.
+    pub triangle_index: u32,
+    /// Average emitted radiance, the material's emissive factor times the
+    /// mean of its emissive texture.
+    pub radiance: glam::Vec3,
+    /// World-space area.
+    pub area: f32,
+    /// Emitted power of a lambertian emitter, `pi * area * luminance(radiance)`.
+    pub power: f32,
+}
+
+/// Entry of an alias table, see [`build_alias_table`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AliasEntry {
+    /// Probability of keeping the sampled slot instead of jumping to `alias`.
+    pub probability: f32,
+    pub alias: u32,
+}
+
+/// Builds a Walker/Vose alias table sampling index `i` with probability
+/// proportional to `weights[i]`.
+///
+/// To sample, pick a slot `i` uniformly, then return `i` if a second uniform
+/// number is below `table[i].probability` and `table[i].alias` otherwise.
+pub fn build_alias_table(weights: &[f32]) -> Vec<AliasEntry> {
+    let total = weights.iter().sum::<f32>();
+    let n = weights.len();
+    if total <= 0.0 {
+        return (0..n as u32)
+            .map(|alias| AliasEntry {
+                probability: 1.0,
+                alias,
+            })
+            .collect();
+    }
+    let mut scaled = weights
+        .iter()
+        .map(|w| w * n as f32 / total)
+        .collect::<Vec<_>>();
+    let mut table = (0..n as u32)
+        .map(|alias| AliasEntry {
+            probability: 1.0,
+            alias,
+        })
+        .collect::<Vec<_>>();
+    let (mut small, mut large): (Vec<_>, Vec<_>) = (0..n).partition(|i| scaled[*i] < 1.0);
+    while let (Some(s), Some(l)) = (small.pop(), large.pop()) {
+        table[s] = AliasEntry {
+            probability: scaled[s],
+            alias: l as u32,
+        };
+        scaled[l] -= 1.0 - scaled[s];
+        if scaled[l] < 1.0 {
+            small.push(l);
+        } else {
+            large.push(l);
+        }
+    }
+    // whatever is left over is 1 up to rounding error and keeps its own slot
+    table
+}
+
+/// std430 record of [`bindings::EMISSIVE_TRIANGLE_BUFFER`](crate::bindings::EMISSIVE_TRIANGLE_BUFFER).
+///
+/// `probability` and `alias` form an alias table over the whole buffer, and
+/// `pdf` is the probability of picking this triangle.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct GpuEmissiveTriangle {
+    pub instance_index: u32,
+    pub geometry_index: u32,
+    pub triangle_index: u32,
+    pub alias: u32,
+    pub radiance: [f32; 3],
+    pub probability: f32,
+    pub area: f32,
+    pub power: f32,
+    pub pdf: f32,
+    pub _padding: u32,
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn luminance(color: glam::Vec3) -> f32 {
+    color.dot(glam::Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+/// Average emitted radiance of every material, zero for materials that do not emit.
+pub(crate) fn material_radiance(
+    material_infos: &[MaterialInfo],
+    gltf_images: &[gltf::image::Data],
+) -> Vec<glam::Vec3> {
+    let mut texture_means = std::collections::HashMap::new();
+    material_infos
+        .iter()
+        .map(|material| {
+            let texture_mean = match material.emissive_texture {
+                Some(texture) if material.emissive_factor != glam::Vec3::ZERO => {
+                    *texture_means.entry(texture.image_index).or_insert_with(|| {
+                        let image = crate::util::convert_image_to_rgba8(
+                            &gltf_images[texture.image_index as usize],
+                        );
+                        let sum = image.pixels().fold(glam::Vec3::ZERO, |sum, p| {
+                            sum + glam::Vec3::new(
+                                srgb_to_linear(p[0]),
+                                srgb_to_linear(p[1]),
+                                srgb_to_linear(p[2]),
+                            )
+                        });
+                        sum / (image.width() * image.height()).max(1) as f32
+                    })
+                }
+                _ => glam::Vec3::ONE,
+            };
+            material.emissive_factor * texture_mean
+        })
+        .collect()
+}
+
+/// Finds the emissive triangles of every visible instance.
+pub(crate) fn gather_emissive_triangles(
+    instance_infos: &[InstanceInfo],
+    mesh_infos: &[MeshInfo],
+    geometry: &MeshGeometry,
+    material_radiance: &[glam::Vec3],
+    root_transform: &glam::Mat4,
+) -> Vec<EmissiveTriangle> {
+    let mut triangles = Vec::new();
+    for instance_index in 0..instance_infos.len() {
+        gather_instance_emissive_triangles(
+            &mut triangles,
+            instance_index,
+            instance_infos,
+            mesh_infos,
+            geometry,
+            material_radiance,
+            root_transform,
+        );
+    }
+    triangles
+}
+
+/// Appends the emissive triangles of instance `instance_index` to
+/// `triangles`, nothing when it is hidden.
+pub(crate) fn gather_instance_emissive_triangles(
+    triangles: &mut Vec<EmissiveTriangle>,
+    instance_index: usize,
+    instance_infos: &[InstanceInfo],
+    mesh_infos: &[MeshInfo],
+    geometry: &MeshGeometry,
+    material_radiance: &[glam::Vec3],
+    root_transform: &glam::Mat4,
+) {
+    let instance = &instance_infos[instance_index];
+    if !instance.visible {
+        return;
+    }
+    let mesh = &mesh_infos[instance.mesh_index];
+    let transform = *root_transform * instance.transform;
+    for (primitive_index, primitive) in mesh.primitive_infos.iter().enumerate() {
+        let radiance = material_radiance[primitive.material_index as usize];
+        if luminance(radiance) <= 0.0 {
+            continue;
+        }
+        let positions = geometry.primitive_positions(primitive);
+        for (triangle_index, indices) in geometry
+            .primitive_indices(primitive)
+            .chunks_exact(3)
+            .enumerate()
+        {
+            let [a, b, c] = [indices[0], indices[1], indices[2]]
+                .map(|i| transform.transform_point3(glam::Vec3::from(positions[i as usize])));
+            let area = 0.5 * (b - a).cross(c - a).length();
+            if area <= 0.0 {
+                continue;
+            }
+            triangles.push(EmissiveTriangle {
+                instance_index,
+                geometry_index: instance.custom_index + primitive_index as u32,
+                triangle_index: triangle_index as u32,
+                radiance,
+                area,
+                power: std::f32::consts::PI * area * luminance(radiance),
+            });
+        }
+    }
+}
+
+pub(crate) fn gather_gpu_emissive_triangles(
+    triangles: &[EmissiveTriangle],
+) -> Vec<GpuEmissiveTriangle> {
+    let powers = triangles.iter().map(|t| t.power).collect::<Vec<_>>();
+    let total_power = powers.iter().sum::<f32>();
+    build_alias_table(&powers)
+        .into_iter()
+        .zip(triangles)
+        .map(|(entry, triangle)| GpuEmissiveTriangle {
+            instance_index: triangle.instance_index as u32,
+            geometry_index: triangle.geometry_index,
+            triangle_index: triangle.triangle_index,
+            alias: entry.alias,
+            radiance: triangle.radiance.to_array(),
+            probability: entry.probability,
+            area: triangle.area,
+            power: triangle.power,
+            pdf: triangle.power / total_power,
+            _padding: 0,
+        })
+        .collect()
+}
+
+#[test]
+fn test_build_alias_table() {
+    let weights = [1.0, 3.0, 0.0, 4.0];
+    let table = build_alias_table(&weights);
+    let mut probabilities = [0.0; 4];
+    for (i, entry) in table.iter().enumerate() {
+        probabilities[i] += entry.probability / 4.0;
+        probabilities[entry.alias as usize] += (1.0 - entry.probability) / 4.0;
+    }
+    for (p, w) in probabilities.iter().zip(&weights) {
+        assert!((p - w / 8.0).abs() < 1e-6);
+    }
+}
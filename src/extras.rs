@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use gltf::json::Value;
+
+use crate::raw_json::RawJson;
+use crate::support::ExtensionSupport;
+
+/// The `extras` of a glTF object and the extensions on it the loader does
+/// not interpret, as written in the document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Extras {
+    pub extras: Option<Value>,
+    /// Extension objects keyed by extension name.
+    pub extensions: BTreeMap<String, Value>,
+}
+
+impl Extras {
+    /// Field `key` of the `extras` object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.extras.as_ref()?.get(key)
+    }
+}
+
+/// Reads the extras of object `index` in `collection`, `None` when it has
+/// neither extras nor unknown extensions.
+pub(crate) fn load(raw_json: &RawJson, collection: &str, index: usize) -> Option<Arc<Extras>> {
+    let object = raw_json.object(collection, index)?;
+    let extras = object.get("extras").cloned();
+    let extensions = object
+        .get("extensions")
+        .and_then(|e| e.as_object())
+        .map(|extensions| {
+            extensions
+                .iter()
+                .filter(|(name, _)| ExtensionSupport::of(name) == ExtensionSupport::Ignored)
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect::<BTreeMap<_, _>>()
+        })
+        .unwrap_or_default();
+    match (&extras, extensions.is_empty()) {
+        (None, true) => None,
+        _ => Some(Arc::new(Extras { extras, extensions })),
+    }
+}
+
+/// Extras of every object in `collection`, indexed like it.
+pub(crate) fn load_all(
+    raw_json: &RawJson,
+    collection: &str,
+    count: usize,
+) -> Vec<Option<Arc<Extras>>> {
+    (0..count)
+        .map(|index| load(raw_json, collection, index))
+        .collect()
+}
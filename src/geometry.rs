@@ -0,0 +1,54 @@
+use crate::PrimitiveInfo;
+
+/// CPU copies of the geometry streams uploaded by `process_meshes`, used for
+/// deformation and other CPU side queries.
+#[derive(Clone, Default)]
+pub(crate) struct MeshGeometry {
+    pub indices: Vec<u32>,
+    pub positions: Vec<[f32; 3]>,
+    pub joints: Vec<[u32; 4]>,
+    pub weights: Vec<[f32; 4]>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub colors: Vec<[f32; 4]>,
+}
+
+impl MeshGeometry {
+    /// Indices of a primitive, relative to its first vertex.
+    pub fn primitive_indices(&self, primitive: &PrimitiveInfo) -> &[u32] {
+        let start = (primitive.index_offset / 4) as usize;
+        &self.indices[start..start + primitive.index_count as usize]
+    }
+
+    pub fn primitive_positions(&self, primitive: &PrimitiveInfo) -> &[[f32; 3]] {
+        let start = primitive.first_vertex as usize;
+        &self.positions[start..start + primitive.vertex_count as usize]
+    }
+
+    pub fn primitive_joints(&self, primitive: &PrimitiveInfo) -> Option<&[[u32; 4]]> {
+        primitive.joints_offset.map(|offset| {
+            let start = (offset / 16) as usize;
+            &self.joints[start..start + primitive.vertex_count as usize]
+        })
+    }
+
+    pub fn primitive_tex_coords(&self, primitive: &PrimitiveInfo) -> Option<&[[f32; 2]]> {
+        primitive.tex_coord_offset.map(|offset| {
+            let start = (offset / 8) as usize;
+            &self.tex_coords[start..start + primitive.vertex_count as usize]
+        })
+    }
+
+    pub fn primitive_colors(&self, primitive: &PrimitiveInfo) -> Option<&[[f32; 4]]> {
+        primitive.color_offset.map(|offset| {
+            let start = (offset / 16) as usize;
+            &self.colors[start..start + primitive.vertex_count as usize]
+        })
+    }
+
+    pub fn primitive_weights(&self, primitive: &PrimitiveInfo) -> Option<&[[f32; 4]]> {
+        primitive.weights_offset.map(|offset| {
+            let start = (offset / 16) as usize;
+            &self.weights[start..start + primitive.vertex_count as usize]
+        })
+    }
+}
@@ -0,0 +1,167 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::{InstanceInfo, MaterialInfo, MeshInfo, PrimitiveInfo, Texture};
+
+/// Marks an absent texture or vertex attribute in the GPU records.
+pub const INVALID_INDEX: u32 = u32::MAX;
+
+/// Largest instance custom index, which Vulkan stores in 24 bits.
+pub const MAX_CUSTOM_INDEX: u32 = (1 << 24) - 1;
+
+const NO_TEXTURE: Texture = Texture {
+    sampler_index: INVALID_INDEX,
+    image_index: INVALID_INDEX,
+};
+
+/// std430 layout of [`MaterialInfo`] as stored in [`Scene::material_buffer`](crate::Scene::material_buffer).
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct GpuMaterialInfo {
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Texture,
+    pub metallic_roughness_texture: Texture,
+    pub packed_texture: Texture,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// See [`alpha_mode_to_u32`].
+    pub alpha_mode: u32,
+    pub alpha_cutoff: f32,
+    pub emissive_texture: Texture,
+    pub emissive_factor: [f32; 3],
+    pub _padding: u32,
+}
+
+/// Encodes an alpha mode for shaders: 0 is opaque, 1 is mask and 2 is blend.
+pub fn alpha_mode_to_u32(alpha_mode: gltf::material::AlphaMode) -> u32 {
+    match alpha_mode {
+        gltf::material::AlphaMode::Opaque => 0,
+        gltf::material::AlphaMode::Mask => 1,
+        gltf::material::AlphaMode::Blend => 2,
+    }
+}
+
+impl From<&MaterialInfo> for GpuMaterialInfo {
+    fn from(info: &MaterialInfo) -> Self {
+        Self {
+            base_color_factor: info.base_color_factor.to_array(),
+            base_color_texture: info.base_color_texture.unwrap_or(NO_TEXTURE),
+            metallic_roughness_texture: info.metallic_roughness_texture.unwrap_or(NO_TEXTURE),
+            packed_texture: info.packed_texture.unwrap_or(NO_TEXTURE),
+            metallic_factor: info.metallic_factor,
+            roughness_factor: info.roughness_factor,
+            alpha_mode: alpha_mode_to_u32(info.alpha_mode),
+            alpha_cutoff: info.alpha_cutoff,
+            emissive_texture: info.emissive_texture.unwrap_or(NO_TEXTURE),
+            emissive_factor: info.emissive_factor.to_array(),
+            _padding: 0,
+        }
+    }
+}
+
+/// std430 layout of [`PrimitiveInfo`] as stored in [`Scene::primitive_buffer`](crate::Scene::primitive_buffer).
+///
+/// Offsets are in elements rather than bytes: `u32` indices, `vec3` positions,
+/// `vec4` colors, `vec2` texture coordinates and `uvec2` quantized positions.
+/// `vertex_offset` is [`INVALID_INDEX`] for primitives with quantized positions,
+/// whose components are multiplied by `position_scale` once unpacked as integers.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct GpuPrimitiveInfo {
+    pub index_offset: u32,
+    pub vertex_offset: u32,
+    pub index_count: u32,
+    pub vertex_count: u32,
+    pub material_index: u32,
+    pub color_offset: u32,
+    pub tex_coord_offset: u32,
+    /// See [`alpha_mode_to_u32`].
+    pub alpha_mode: u32,
+    /// A [`PositionFormat`](crate::PositionFormat) as `u32`.
+    pub position_format: u32,
+    pub quantized_vertex_offset: u32,
+    pub position_scale: f32,
+}
+
+impl From<&PrimitiveInfo> for GpuPrimitiveInfo {
+    fn from(info: &PrimitiveInfo) -> Self {
+        Self {
+            index_offset: (info.index_offset / 4) as u32,
+            vertex_offset: match info.quantized_vertex_offset {
+                Some(_) => INVALID_INDEX,
+                None => (info.vertex_offset / 12) as u32,
+            },
+            index_count: info.index_count as u32,
+            vertex_count: info.vertex_count as u32,
+            material_index: info.material_index as u32,
+            color_offset: info.color_offset.map_or(INVALID_INDEX, |o| (o / 16) as u32),
+            tex_coord_offset: info
+                .tex_coord_offset
+                .map_or(INVALID_INDEX, |o| (o / 8) as u32),
+            alpha_mode: alpha_mode_to_u32(info.alpha_mode),
+            position_format: info.position_format as u32,
+            quantized_vertex_offset: info
+                .quantized_vertex_offset
+                .map_or(INVALID_INDEX, |o| (o / 8) as u32),
+            position_scale: info.position_scale,
+        }
+    }
+}
+
+/// One record per geometry of every TLAS instance, stored in
+/// [`Scene::geometry_buffer`](crate::Scene::geometry_buffer).
+///
+/// Records are laid out so that hit shaders find theirs at
+/// `gl_InstanceCustomIndexEXT + gl_GeometryIndexEXT`. Offsets are in elements,
+/// as in [`GpuPrimitiveInfo`].
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct GpuGeometryInfo {
+    pub index_offset: u32,
+    pub vertex_offset: u32,
+    pub color_offset: u32,
+    pub tex_coord_offset: u32,
+    pub material_index: u32,
+    pub instance_index: u32,
+    pub mesh_index: u32,
+    pub primitive_index: u32,
+    pub position_format: u32,
+    pub quantized_vertex_offset: u32,
+    pub position_scale: f32,
+}
+
+/// Whether the custom index of every instance fits in 24 bits. Custom indices
+/// grow with instance order, so only the last one needs checking.
+pub(crate) fn custom_indices_fit(instance_infos: &[InstanceInfo]) -> bool {
+    instance_infos.last().map_or(0, |info| info.custom_index) <= MAX_CUSTOM_INDEX
+}
+
+pub(crate) fn gather_geometry_infos(
+    instance_infos: &[InstanceInfo],
+    mesh_infos: &[MeshInfo],
+) -> Vec<GpuGeometryInfo> {
+    let mut geometry_infos = Vec::new();
+    for (instance_index, instance) in instance_infos.iter().enumerate() {
+        debug_assert_eq!(instance.custom_index as usize, geometry_infos.len());
+        for (primitive_index, primitive) in mesh_infos[instance.mesh_index]
+            .primitive_infos
+            .iter()
+            .enumerate()
+        {
+            let primitive = GpuPrimitiveInfo::from(primitive);
+            geometry_infos.push(GpuGeometryInfo {
+                index_offset: primitive.index_offset,
+                vertex_offset: primitive.vertex_offset,
+                color_offset: primitive.color_offset,
+                tex_coord_offset: primitive.tex_coord_offset,
+                material_index: primitive.material_index,
+                instance_index: instance_index as u32,
+                mesh_index: instance.mesh_index as u32,
+                primitive_index: primitive_index as u32,
+                position_format: primitive.position_format,
+                quantized_vertex_offset: primitive.quantized_vertex_offset,
+                position_scale: primitive.position_scale,
+            });
+        }
+    }
+    geometry_infos
+}
@@ -0,0 +1,168 @@
+use std::path::Path;
+
+use crate::draco;
+use crate::meshopt;
+use crate::raw_json::RawJson;
+
+/// Everything read from disk for one document.
+pub(crate) struct Import {
+    pub doc: gltf::Document,
+    pub buffers: Vec<gltf::buffer::Data>,
+    pub images: Vec<gltf::image::Data>,
+    pub raw_json: RawJson,
+}
+
+/// Replaces `%XX` escapes in a URI path, keeping malformed ones as they are.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i + 1..i + 3) {
+            Some(hex) if bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit) => {
+                std::str::from_utf8(hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            }
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn read_uri(base: &Path, uri: &str) -> Result<Vec<u8>, gltf::Error> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let encoded = data.split(";base64,").nth(1).unwrap_or(data);
+        base64::decode(encoded).map_err(gltf::Error::Base64)
+    } else if let Some(path) = uri
+        .strip_prefix("file://")
+        .or_else(|| uri.strip_prefix("file:"))
+    {
+        Ok(std::fs::read(percent_decode(path))?)
+    } else if uri.contains(':') {
+        Err(gltf::Error::UnsupportedScheme)
+    } else {
+        Ok(std::fs::read(base.join(percent_decode(uri)))?)
+    }
+}
+
+/// Validates `document` like `gltf::Gltf::from_slice` does, except that the
+/// accessors of Draco compressed primitives may lack a buffer view.
+pub(crate) fn validate(document: &gltf::Document, raw_json: &RawJson) -> Result<(), gltf::Error> {
+    use gltf::json::validation::{Error, Validate};
+
+    let compressed = draco::compressed_accessors(document, raw_json)
+        .into_iter()
+        .map(|index| format!("accessors[{}].bufferView", index))
+        .collect::<Vec<_>>();
+    let root = document.clone().into_json();
+    let mut errors = Vec::new();
+    root.validate(&root, gltf::json::Path::new, &mut |path, error| {
+        let path = path();
+        if error != Error::Missing || !compressed.iter().any(|c| c == path.as_str()) {
+            errors.push((path, error));
+        }
+    });
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(gltf::Error::Validation(errors)),
+    }
+}
+
+fn decode_image(encoded: &[u8]) -> Result<gltf::image::Data, gltf::Error> {
+    use gltf::image::Format;
+    use image::{DynamicImage, GenericImageView};
+
+    let image = image::load_from_memory(encoded)?;
+    let format = match image {
+        DynamicImage::ImageLuma8(_) => Format::R8,
+        DynamicImage::ImageLumaA8(_) => Format::R8G8,
+        DynamicImage::ImageRgb8(_) => Format::R8G8B8,
+        DynamicImage::ImageRgba8(_) => Format::R8G8B8A8,
+        DynamicImage::ImageBgr8(_) => Format::B8G8R8,
+        DynamicImage::ImageBgra8(_) => Format::B8G8R8A8,
+        DynamicImage::ImageLuma16(_) => Format::R16,
+        DynamicImage::ImageLumaA16(_) => Format::R16G16,
+        DynamicImage::ImageRgb16(_) => Format::R16G16B16,
+        DynamicImage::ImageRgba16(_) => Format::R16G16B16A16,
+    };
+    let (width, height) = image.dimensions();
+    Ok(gltf::image::Data {
+        pixels: image.to_bytes(),
+        format,
+        width,
+        height,
+    })
+}
+
+/// Like `gltf::import`, except that buffers which only exist as
+/// `EXT_meshopt_compression` fallbacks are allocated rather than read. They
+/// are filled by [`meshopt::decode_buffer_views`].
+pub(crate) fn import(path: &Path) -> Result<Import, gltf::Error> {
+    let base = path.parent().unwrap_or_else(|| Path::new("./"));
+    let data = std::fs::read(path)?;
+    let raw_json = RawJson::from_slice(&data)?;
+    let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice_without_validation(&data)?;
+    validate(&document, &raw_json)?;
+
+    let mut buffers = Vec::new();
+    for buffer in document.buffers() {
+        let mut data = if meshopt::is_fallback_buffer(&raw_json, buffer.index()) {
+            vec![0; buffer.length()]
+        } else {
+            match buffer.source() {
+                gltf::buffer::Source::Uri(uri) => read_uri(base, uri)?,
+                gltf::buffer::Source::Bin => blob.take().ok_or(gltf::Error::MissingBlob)?,
+            }
+        };
+        if data.len() < buffer.length() {
+            return Err(gltf::Error::BufferLength {
+                buffer: buffer.index(),
+                expected: buffer.length(),
+                actual: data.len(),
+            });
+        }
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        buffers.push(gltf::buffer::Data(data));
+    }
+
+    let mut images = Vec::new();
+    for image in document.images() {
+        let image = match image.source() {
+            gltf::image::Source::Uri { uri, .. } => decode_image(&read_uri(base, uri)?)?,
+            gltf::image::Source::View { view, .. } => decode_image(
+                &buffers[view.buffer().index()][view.offset()..view.offset() + view.length()],
+            )?,
+        };
+        images.push(image);
+    }
+
+    Ok(Import {
+        doc: document,
+        buffers,
+        images,
+        raw_json,
+    })
+}
+
+#[test]
+fn test_percent_decode() {
+    assert_eq!(
+        percent_decode("my%20model%2Bparts.bin"),
+        "my model+parts.bin"
+    );
+    assert_eq!(percent_decode("caf%C3%A9/100%"), "café/100%");
+    assert_eq!(percent_decode("%zz%4"), "%zz%4");
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use crate::{Extras, MaterialInfo, MeshInfo};
+
+/// Mask and shader binding table offset of a TLAS instance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstanceParams {
+    /// Only recorded in [`InstanceInfo::mask`]: `maligog::BLASInstance` has
+    /// no mask, so TLAS instances are visible to every ray.
+    pub mask: u8,
+    pub sbt_offset: u32,
+}
+
+/// What an [`InstancePolicy`] gets to look at when placing an instance.
+pub struct InstanceContext<'a, 'd> {
+    pub node: &'a gltf::Node<'d>,
+    pub mesh: &'a MeshInfo,
+    /// Materials of the scene, indexed by `PrimitiveInfo::material_index`.
+    pub material_infos: &'a [MaterialInfo],
+}
+
+impl InstanceContext<'_, '_> {
+    /// Materials used by the primitives of the instanced mesh.
+    pub fn materials(&self) -> impl Iterator<Item = &MaterialInfo> {
+        let material_infos = self.material_infos;
+        self.mesh
+            .primitive_infos
+            .iter()
+            .map(move |p| &material_infos[p.material_index as usize])
+    }
+}
+
+/// Decides the mask and SBT record offset of every TLAS instance created
+/// while loading a scene.
+pub trait InstancePolicy: Send + Sync {
+    fn instance_params(&self, context: &InstanceContext) -> InstanceParams;
+}
+
+impl<F> InstancePolicy for F
+where
+    F: Fn(&InstanceContext) -> InstanceParams + Send + Sync,
+{
+    fn instance_params(&self, context: &InstanceContext) -> InstanceParams {
+        self(context)
+    }
+}
+
+/// Visible to every ray and offset into the SBT by mesh index.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MeshIndexPolicy;
+
+impl InstancePolicy for MeshIndexPolicy {
+    fn instance_params(&self, context: &InstanceContext) -> InstanceParams {
+        InstanceParams {
+            mask: 0xff,
+            sbt_offset: context.node.mesh().unwrap().index() as u32,
+        }
+    }
+}
+
+/// Material categories used by [`MaterialCategoryPolicy`], in priority order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MaterialCategory {
+    Opaque = 0,
+    AlphaTested = 1,
+    Blended = 2,
+    Emissive = 3,
+}
+
+impl MaterialCategory {
+    /// The category of an instance is that of its "least opaque" material,
+    /// with emission taking precedence over everything else.
+    pub fn of_materials<'a>(materials: impl IntoIterator<Item = &'a MaterialInfo>) -> Self {
+        materials
+            .into_iter()
+            .map(|m| {
+                if m.emissive_factor != glam::Vec3::ZERO {
+                    MaterialCategory::Emissive
+                } else {
+                    match m.alpha_mode {
+                        gltf::material::AlphaMode::Opaque => MaterialCategory::Opaque,
+                        gltf::material::AlphaMode::Mask => MaterialCategory::AlphaTested,
+                        gltf::material::AlphaMode::Blend => MaterialCategory::Blended,
+                    }
+                }
+            })
+            .max_by_key(|c| *c as u32)
+            .unwrap_or(MaterialCategory::Opaque)
+    }
+}
+
+/// Picks mask and SBT offset from the material category of the instance.
+///
+/// Nodes whose name is listed in `no_shadow_nodes` lose `shadow_mask_bit`.
+/// Masks are only recorded, see [`InstanceParams::mask`].
+#[derive(Clone, Debug)]
+pub struct MaterialCategoryPolicy {
+    /// Indexed by `MaterialCategory as usize`.
+    pub masks: [u8; 4],
+    /// Indexed by `MaterialCategory as usize`.
+    pub sbt_offsets: [u32; 4],
+    pub shadow_mask_bit: u8,
+    pub no_shadow_nodes: Vec<String>,
+}
+
+impl Default for MaterialCategoryPolicy {
+    fn default() -> Self {
+        Self {
+            masks: [0x01 | 0x80, 0x02 | 0x80, 0x04 | 0x80, 0x08 | 0x80],
+            sbt_offsets: [0, 1, 2, 3],
+            shadow_mask_bit: 0x80,
+            no_shadow_nodes: Vec::new(),
+        }
+    }
+}
+
+impl InstancePolicy for MaterialCategoryPolicy {
+    fn instance_params(&self, context: &InstanceContext) -> InstanceParams {
+        let category = MaterialCategory::of_materials(context.materials());
+        let mut mask = self.masks[category as usize];
+        if let Some(name) = context.node.name() {
+            if self.no_shadow_nodes.iter().any(|n| n == name) {
+                mask &= !self.shadow_mask_bit;
+            }
+        }
+        InstanceParams {
+            mask,
+            sbt_offset: self.sbt_offsets[category as usize],
+        }
+    }
+}
+
+/// Describes a TLAS instance created while loading, in TLAS instance order.
+#[derive(Clone, Debug)]
+pub struct InstanceInfo {
+    /// The node that produced the instance, `None` for instances added with
+    /// [`Scene::add_instance`](crate::Scene::add_instance).
+    pub node_index: Option<usize>,
+    /// Which `EXT_mesh_gpu_instancing` copy of the node's mesh this is.
+    pub gpu_instance: Option<usize>,
+    pub mesh_index: usize,
+    /// `gl_InstanceCustomIndexEXT`, the number of primitives of all instances before this one.
+    pub custom_index: u32,
+    /// The mask chosen by the policy, not applied to the TLAS instance.
+    pub mask: u8,
+    pub sbt_offset: u32,
+    pub transform: glam::Mat4,
+    /// Hidden instances stay in the TLAS, with a zero transform that leaves
+    /// nothing to hit, so instance indices don't change.
+    pub visible: bool,
+    /// Extras of the node that produced the instance.
+    pub extras: Option<Arc<Extras>>,
+}
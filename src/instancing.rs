@@ -0,0 +1,128 @@
+use crate::accessor;
+use crate::raw_json::RawJson;
+use crate::InstanceInfo;
+
+pub(crate) const EXTENSION_NAME: &str = "EXT_mesh_gpu_instancing";
+
+/// Copies of a node's mesh placed by `EXT_mesh_gpu_instancing`.
+#[derive(Clone, Debug)]
+pub(crate) struct NodeInstancing {
+    /// Transform of each copy relative to the node.
+    pub transforms: Vec<glam::Mat4>,
+    /// Custom attributes, named with a leading underscore, one value per
+    /// copy padded to four components.
+    pub attributes: Vec<(String, Vec<[f32; 4]>)>,
+}
+
+impl NodeInstancing {
+    pub fn attribute(&self, name: &str, copy: usize) -> Option<[f32; 4]> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, values)| values[copy])
+    }
+}
+
+/// Reads the instancing attributes of every node, indexed by node index.
+pub(crate) fn load_node_instancing(
+    doc: &gltf::Document,
+    raw_json: &RawJson,
+    buffers: &[gltf::buffer::Data],
+) -> Vec<Option<NodeInstancing>> {
+    doc.nodes()
+        .map(|node| {
+            let attributes = raw_json
+                .node_extension(node.index(), EXTENSION_NAME)?
+                .get("attributes")?
+                .as_object()?;
+            let mut attributes = attributes
+                .iter()
+                .filter_map(|(name, accessor_index)| {
+                    let accessor = doc.accessors().nth(accessor_index.as_u64()? as usize)?;
+                    Some((
+                        name.clone(),
+                        accessor::read_vectors::<4>(&accessor, buffers),
+                    ))
+                })
+                .collect::<Vec<_>>();
+            let count = attributes.iter().map(|(_, v)| v.len()).max()?;
+            let take = |attributes: &mut Vec<(String, Vec<[f32; 4]>)>, name: &str| {
+                attributes
+                    .iter()
+                    .position(|(n, _)| n == name)
+                    .map(|i| attributes.remove(i).1)
+            };
+            let translations = take(&mut attributes, "TRANSLATION");
+            let rotations = take(&mut attributes, "ROTATION");
+            let scales = take(&mut attributes, "SCALE");
+            let transforms = (0..count)
+                .map(|i| {
+                    let translation = translations
+                        .as_ref()
+                        .map_or(glam::Vec3::ZERO, |t| glam::Vec4::from(t[i]).truncate());
+                    let rotation = rotations.as_ref().map_or(glam::Quat::IDENTITY, |r| {
+                        glam::Quat::from_array(r[i]).normalize()
+                    });
+                    let scale = scales
+                        .as_ref()
+                        .map_or(glam::Vec3::ONE, |s| glam::Vec4::from(s[i]).truncate());
+                    glam::Mat4::from_scale_rotation_translation(scale, rotation, translation)
+                })
+                .collect();
+            attributes.retain(|(name, _)| name.starts_with('_'));
+            Some(NodeInstancing {
+                transforms,
+                attributes,
+            })
+        })
+        .collect()
+}
+
+/// Names of the custom attributes used by any node, sorted.
+pub(crate) fn attribute_names(instancing: &[Option<NodeInstancing>]) -> Vec<String> {
+    let mut names = instancing
+        .iter()
+        .flatten()
+        .flat_map(|i| i.attributes.iter().map(|(name, _)| name.clone()))
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Transform of an instance relative to its node.
+pub(crate) fn instance_transform(
+    instancing: &[Option<NodeInstancing>],
+    info: &InstanceInfo,
+) -> glam::Mat4 {
+    match (info.node_index, info.gpu_instance) {
+        (Some(node_index), Some(copy)) => instancing[node_index]
+            .as_ref()
+            .map_or(glam::Mat4::IDENTITY, |i| i.transforms[copy]),
+        _ => glam::Mat4::IDENTITY,
+    }
+}
+
+/// Custom attribute values of every TLAS instance, `names.len()` per
+/// instance, zero where an instance lacks an attribute.
+pub(crate) fn gather_instance_attributes(
+    instance_infos: &[InstanceInfo],
+    instancing: &[Option<NodeInstancing>],
+    names: &[String],
+) -> Vec<[f32; 4]> {
+    instance_infos
+        .iter()
+        .flat_map(|info| {
+            let node_instancing = info
+                .node_index
+                .and_then(|node_index| instancing[node_index].as_ref());
+            names.iter().map(move |name| {
+                match (node_instancing, info.gpu_instance) {
+                    (Some(i), Some(copy)) => i.attribute(name, copy),
+                    _ => None,
+                }
+                .unwrap_or_default()
+            })
+        })
+        .collect()
+}
@@ -1,12 +1,76 @@
 #![cfg_attr(debug_assertions, allow(dead_code, unused_imports, unused))]
 
+mod accessor;
+mod animation;
+mod axis;
+mod bounds;
+mod bvh;
+mod camera;
+mod deform;
+mod descriptor;
+mod draco;
+mod emissive;
+mod extras;
+mod geometry;
+mod gpu;
+mod import;
+mod instance;
+mod instancing;
+mod light;
+mod meshopt;
+mod morph;
+mod options;
+mod raw_json;
+mod repack;
+mod sampler;
+mod scene_graph;
+mod skin;
+mod support;
 mod util;
+mod variants;
 
+pub use animation::{Animation, Channel as AnimationChannel, Interpolation, Property};
+pub use axis::{AxisConversion, Handedness, UpAxis};
+pub use bounds::{Aabb, BoundingSphere, GpuAabb};
+pub use bvh::RayHit;
 use bytemuck::{Pod, Zeroable};
+pub use camera::{Camera, ClipConventions, GpuCamera, Projection};
+pub use descriptor::{bindings, SceneDescriptorType, SceneDescriptors, SceneResource};
+pub use draco::{DracoAttribute, DracoDecoder, DracoError, DracoMesh, SequentialDracoDecoder};
+pub use emissive::{build_alias_table, AliasEntry, EmissiveTriangle, GpuEmissiveTriangle};
+pub use extras::Extras;
 pub use gltf;
+pub use gpu::{
+    alpha_mode_to_u32, GpuGeometryInfo, GpuMaterialInfo, GpuPrimitiveInfo, INVALID_INDEX,
+    MAX_CUSTOM_INDEX,
+};
+pub use instance::{
+    InstanceContext, InstanceInfo, InstanceParams, InstancePolicy, MaterialCategory,
+    MaterialCategoryPolicy, MeshIndexPolicy,
+};
+pub use light::{
+    GpuLight, Light, LightKind, LIGHT_TYPE_DIRECTIONAL, LIGHT_TYPE_POINT, LIGHT_TYPE_SPOT,
+};
+pub use meshopt::MeshoptError;
+pub use morph::{blend_morph_targets, blend_positions, MorphTarget};
+pub use options::LoadOptions;
+pub use repack::{pack_channels, Channel, ChannelSource, PackingSpec, TextureSlot};
+pub use sampler::SamplerInfo;
+pub use scene_graph::{NodeTransform, SceneGraph, SceneNode};
+pub use skin::{skin_positions, Skin};
+pub use support::{ExtensionStatus, ExtensionSupport, LoadError, SupportReport};
 
+use std::collections::BTreeSet;
 use std::convert::TryInto;
 use std::path::Path;
+use std::sync::Arc;
+
+use bvh::Bvh;
+use deform::DeformedMesh;
+use geometry::MeshGeometry;
+use instancing::NodeInstancing;
+use raw_json::RawJson;
+use variants::MaterialVariants;
 
 use image::buffer::ConvertBuffer;
 
@@ -16,48 +80,130 @@ use std::any::{Any, TypeId};
 #[derive(Clone, Copy)]
 pub struct PrimitiveInfo {
     pub index_offset: u64,
+    /// Byte offset into [`Scene::vertex_buffer`]. Primitives with quantized
+    /// positions have no range there.
     pub vertex_offset: u64,
     pub index_count: u64,
     pub vertex_count: u64,
+    /// Index of the first vertex in the CPU copy of the geometry.
+    pub(crate) first_vertex: u64,
+    pub position_format: PositionFormat,
+    /// Dequantizes positions: object space positions are the stored
+    /// components times this, see [`PositionFormat::scale`].
+    pub position_scale: f32,
+    /// Byte offset into [`Scene::quantized_vertex_buffer`], set unless
+    /// `position_format` is `Float`.
+    pub quantized_vertex_offset: Option<u64>,
     pub material_index: u64,
     pub color_offset: Option<u64>,
     pub tex_coord_offset: Option<u64>,
+    /// Byte offset of `JOINTS_0` as `uvec4` in [`Scene::joint_buffer`].
+    pub joints_offset: Option<u64>,
+    /// Byte offset of `WEIGHTS_0` as `vec4` in [`Scene::weight_buffer`].
+    pub weights_offset: Option<u64>,
+    /// Alpha mode of the primitive's material. maligog's `TriangleGeometry`
+    /// takes no geometry flags, so the BLASes do not flag primitives that are
+    /// not `Opaque` differently from opaque ones.
+    pub alpha_mode: gltf::material::AlphaMode,
+    /// Object space bounds of the positions.
+    pub bounds: Aabb,
+}
+
+impl PrimitiveInfo {
+    pub fn is_opaque(&self) -> bool {
+        self.alpha_mode == gltf::material::AlphaMode::Opaque
+    }
+}
+
+/// How the positions of a primitive are stored on the GPU, see
+/// [`LoadOptions::quantized_positions`].
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionFormat {
+    /// `vec3` in [`Scene::vertex_buffer`].
+    Float = 0,
+    /// Four `int16` per vertex in [`Scene::quantized_vertex_buffer`], the last
+    /// one padding, read with `unpackSnorm2x16`.
+    Snorm16 = 1,
+    /// As `Snorm16`, read with `unpackUnorm2x16`. Only kept when
+    /// [`LoadOptions::unorm16_vertex_format`] is set.
+    Unorm16 = 2,
+}
+
+impl PositionFormat {
+    /// Factor taking the stored integer components to normalized values,
+    /// leaving aside the clamp of `-32768` to `-1.0` for `Snorm16`.
+    pub fn scale(self) -> f32 {
+        match self {
+            PositionFormat::Float => 1.0,
+            PositionFormat::Snorm16 => 1.0 / i16::MAX as f32,
+            PositionFormat::Unorm16 => 1.0 / u16::MAX as f32,
+        }
+    }
 }
 
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Pod, Zeroable)]
 pub struct Texture {
     pub sampler_index: u32,
     pub image_index: u32,
 }
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct MaterialInfo {
     pub base_color_factor: glam::Vec4,
     pub base_color_texture: Option<Texture>,
     pub metallic_roughness_texture: Option<Texture>,
     metallic_factor: f32,
     roughness_factor: f32,
+    pub alpha_mode: gltf::material::AlphaMode,
+    pub alpha_cutoff: f32,
+    pub emissive_factor: glam::Vec3,
+    pub emissive_texture: Option<Texture>,
+    /// Texture built by [`LoadOptions::texture_packing`], if any.
+    pub packed_texture: Option<Texture>,
+    pub extras: Option<Arc<Extras>>,
 }
 
 #[derive(Clone)]
 pub struct MeshInfo {
     pub name: Option<String>,
     pub primitive_infos: Vec<PrimitiveInfo>,
+    /// Morph targets of each primitive, indexed like `primitive_infos`.
+    pub morph_targets: Vec<Vec<MorphTarget>>,
+    /// Default morph target weights of the mesh.
+    pub weights: Vec<f32>,
+    pub extras: Option<Arc<Extras>>,
+    /// Object space bounds of all primitives, in their rest pose.
+    pub bounds: Aabb,
+}
+
+impl MeshInfo {
+    pub fn has_morph_targets(&self) -> bool {
+        self.morph_targets.iter().any(|t| !t.is_empty())
+    }
 }
 
 #[derive(Clone)]
 struct MeshData {
     index_buffer: maligog::Buffer,
     vertex_buffer: maligog::Buffer,
+    quantized_vertex_buffer: Option<maligog::Buffer>,
     color_buffer: Option<maligog::Buffer>,
     tex_coord_buffer: Option<maligog::Buffer>,
+    joint_buffer: Option<maligog::Buffer>,
+    weight_buffer: Option<maligog::Buffer>,
+    primitive_buffer: maligog::Buffer,
     mesh_infos: Vec<MeshInfo>,
+    geometry: MeshGeometry,
 }
 
 #[derive(Clone)]
 pub struct InstanceData {
     transform_buffer: maligog::Buffer,
+    geometry_buffer: maligog::Buffer,
+    attribute_buffer: Option<maligog::Buffer>,
+    bounds_buffer: Option<maligog::Buffer>,
 }
 
 #[derive(Clone)]
@@ -65,11 +211,52 @@ pub struct Scene {
     images: Vec<maligog::Image>,
     tlas: maligog::TopAccelerationStructure,
     samplers: Vec<maligog::Sampler>,
+    sampler_infos: Vec<SamplerInfo>,
     doc: gltf::Document,
     mesh_data: MeshData,
     instance_data: InstanceData,
     load_time: std::time::Instant,
     material_infos: Vec<MaterialInfo>,
+    material_buffer: maligog::Buffer,
+    empty_buffer: maligog::Buffer,
+    instance_infos: Vec<InstanceInfo>,
+    blases: Vec<maligog::BottomAccelerationStructure>,
+    /// BLASes with reversed winding, built for meshes once an instance of
+    /// them mirrors.
+    flipped_blases: Vec<Option<maligog::BottomAccelerationStructure>>,
+    scene_graph: SceneGraph,
+    animations: Vec<Animation>,
+    pending_instance_update: Option<InstanceUpdate>,
+    /// Instances moved, shown or hidden since the last commit.
+    changed_instances: BTreeSet<usize>,
+    skins: Vec<Skin>,
+    deformed_meshes: Vec<DeformedMesh>,
+    joint_matrix_buffer: Option<maligog::Buffer>,
+    cameras: Vec<Camera>,
+    lights: Vec<Light>,
+    light_buffer: Option<maligog::Buffer>,
+    material_radiance: Vec<glam::Vec3>,
+    emissive_triangles: Vec<EmissiveTriangle>,
+    emissive_triangle_buffer: Option<maligog::Buffer>,
+    instancing: Vec<Option<NodeInstancing>>,
+    instance_attribute_names: Vec<String>,
+    material_variants: MaterialVariants,
+    support_report: SupportReport,
+    root_transform: glam::Mat4,
+    normalization: glam::Mat4,
+    instance_bounds: Vec<Aabb>,
+    mesh_bvhs: Vec<Bvh>,
+}
+
+/// How much of the instance data has to be brought up to date with edited
+/// instances. The TLAS is rebuilt either way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum InstanceUpdate {
+    /// Only transforms, visibility or deformed vertices changed, custom
+    /// indices and the geometry buffer stay valid.
+    Transforms,
+    /// Instances were added or removed, custom indices are reassigned.
+    Rebuild,
 }
 
 impl PartialEq for Scene {
@@ -100,6 +287,27 @@ fn create_device_buffers(
         .collect::<Vec<_>>()
 }
 
+fn create_packed_device_images(
+    device: &maligog::Device,
+    packed_images: &[image::RgbaImage],
+) -> Vec<maligog::Image> {
+    packed_images
+        .iter()
+        .map(|image| {
+            let bgra8: image::ImageBuffer<image::Bgra<u8>, Vec<u8>> = image.convert();
+            device.create_image_init(
+                Some("packed texture"),
+                maligog::Format::B8G8R8A8_UNORM,
+                image.width(),
+                image.height(),
+                maligog::ImageUsageFlags::SAMPLED,
+                maligog::MemoryLocation::GpuOnly,
+                bgra8.as_raw(),
+            )
+        })
+        .collect::<Vec<_>>()
+}
+
 fn create_device_images(
     device: &maligog::Device,
     gltf_images: &[gltf::image::Data],
@@ -125,159 +333,407 @@ fn create_device_images(
 fn create_samlers(
     device: &maligog::Device,
     gltf_samplers: gltf::iter::Samplers,
+    sampler_infos: &[SamplerInfo],
 ) -> Vec<maligog::Sampler> {
-    let mut samplers = vec![device.create_sampler(
-        // default sampler
-        Some("default sampler"),
-        maligog::Filter::LINEAR,
-        maligog::Filter::LINEAR,
-        maligog::SamplerAddressMode::CLAMP_TO_EDGE,
-        maligog::SamplerAddressMode::CLAMP_TO_EDGE,
-    )];
-    for sampler in gltf_samplers {
-        let mag_filter = if let Some(mag_filter) = sampler.mag_filter() {
-            match mag_filter {
-                gltf::texture::MagFilter::Nearest => maligog::Filter::NEAREST,
-                gltf::texture::MagFilter::Linear => maligog::Filter::LINEAR,
-            }
-        } else {
-            maligog::Filter::LINEAR
-        };
-
-        let min_filter = if let Some(min_filter) = sampler.min_filter() {
-            match min_filter {
-                gltf::texture::MinFilter::Nearest => maligog::Filter::NEAREST,
-                gltf::texture::MinFilter::Linear => maligog::Filter::LINEAR,
-                gltf::texture::MinFilter::NearestMipmapNearest => maligog::Filter::NEAREST,
-                gltf::texture::MinFilter::LinearMipmapNearest => maligog::Filter::LINEAR,
-                gltf::texture::MinFilter::NearestMipmapLinear => maligog::Filter::NEAREST,
-                gltf::texture::MinFilter::LinearMipmapLinear => maligog::Filter::LINEAR,
-            }
-        } else {
-            maligog::Filter::LINEAR
-        };
-
-        let address_mode_u = match sampler.wrap_s() {
-            gltf::texture::WrappingMode::ClampToEdge => maligog::SamplerAddressMode::CLAMP_TO_EDGE,
-            gltf::texture::WrappingMode::MirroredRepeat => {
-                maligog::SamplerAddressMode::MIRRORED_REPEAT
-            }
-            gltf::texture::WrappingMode::Repeat => maligog::SamplerAddressMode::REPEAT,
-        };
-        let address_mode_v = match sampler.wrap_t() {
-            gltf::texture::WrappingMode::ClampToEdge => maligog::SamplerAddressMode::CLAMP_TO_EDGE,
-            gltf::texture::WrappingMode::MirroredRepeat => {
-                maligog::SamplerAddressMode::MIRRORED_REPEAT
-            }
-            gltf::texture::WrappingMode::Repeat => maligog::SamplerAddressMode::REPEAT,
-        };
-        samplers.push(device.create_sampler(
-            sampler.name(),
-            mag_filter,
-            min_filter,
-            address_mode_u,
-            address_mode_v,
-        ));
+    let create_sampler = |name: Option<&str>, info: &SamplerInfo| {
+        device.create_sampler(
+            name,
+            info.mag_filter,
+            info.min_filter,
+            info.address_mode_u,
+            info.address_mode_v,
+        )
+    };
+    let mut samplers = vec![create_sampler(Some("default sampler"), &sampler_infos[0])];
+    for (sampler, info) in gltf_samplers.zip(&sampler_infos[1..]) {
+        samplers.push(create_sampler(sampler.name(), info));
     }
     samplers
 }
 
+struct InstanceBuildContext<'a> {
+    mesh_infos: &'a [MeshInfo],
+    material_infos: &'a [MaterialInfo],
+    policy: &'a dyn InstancePolicy,
+    instancing: &'a [Option<NodeInstancing>],
+    node_extras: &'a [Option<Arc<Extras>>],
+}
+
 fn process_node(
-    device: &maligog::Device,
+    context: &InstanceBuildContext,
     node: &gltf::Node,
-    blases: &[maligog::BottomAccelerationStructure],
     instance_offset: &mut u32,
     parent_tranform: &glam::Mat4,
-) -> Vec<maligog::BLASInstance> {
+    instance_infos: &mut Vec<InstanceInfo>,
+) {
     let node_relative_transform = util::gltf_to_glam_tranform(&node.transform());
     let node_absolute_transform: glam::Mat4 = *parent_tranform * node_relative_transform;
-    let mut instances = Vec::new();
     if let Some(mesh) = node.mesh() {
-        instances.push(maligog::BLASInstance::new(
-            &device,
-            &blases.get(mesh.index()).unwrap(),
+        let mesh_info = &context.mesh_infos[mesh.index()];
+        let params = context.policy.instance_params(&InstanceContext {
+            node,
+            mesh: mesh_info,
+            material_infos: context.material_infos,
+        });
+        // EXT_mesh_gpu_instancing expands into one TLAS instance per copy
+        let copies = match &context.instancing[node.index()] {
+            Some(instancing) => instancing
+                .transforms
+                .iter()
+                .enumerate()
+                .map(|(copy, transform)| (Some(copy), *transform))
+                .collect(),
+            None => vec![(None, glam::Mat4::IDENTITY)],
+        };
+        for (gpu_instance, instance_transform) in copies {
+            instance_infos.push(InstanceInfo {
+                node_index: Some(node.index()),
+                gpu_instance,
+                mesh_index: mesh.index(),
+                custom_index: *instance_offset,
+                mask: params.mask,
+                sbt_offset: params.sbt_offset,
+                transform: node_absolute_transform * instance_transform,
+                visible: true,
+                extras: context.node_extras[node.index()].clone(),
+            });
+            *instance_offset += mesh.primitives().len() as u32;
+        }
+    }
+    for child in node.children() {
+        process_node(
+            context,
+            &child,
+            instance_offset,
             &node_absolute_transform,
-            *instance_offset,
-            mesh.index() as u32,
-        ));
-        *instance_offset += mesh.primitives().len() as u32;
-    }
-    instances.extend(
-        node.children()
-            .map(|n| {
-                process_node(
-                    &device,
-                    &n,
-                    blases,
-                    instance_offset,
-                    &node_absolute_transform,
-                )
-            })
-            .flatten()
-            .collect::<Vec<_>>(),
-    );
-    instances
+            instance_infos,
+        );
+    }
+}
+
+fn gather_instance_infos(context: &InstanceBuildContext, scene: &gltf::Scene) -> Vec<InstanceInfo> {
+    let mut instance_offset = 0;
+    let mut instance_infos = Vec::new();
+    for node in scene.nodes() {
+        process_node(
+            context,
+            &node,
+            &mut instance_offset,
+            &glam::Mat4::IDENTITY,
+            &mut instance_infos,
+        );
+    }
+    instance_infos
 }
 
+/// Creates the TLAS instances, placing each at `root_transform` times its
+/// instance transform.
+///
+/// `maligog::BLASInstance` takes no `TRIANGLE_FLIP_FACING` flag, so instances
+/// whose transform mirrors use the flipped BLAS of their mesh instead, see
+/// [`create_flipped_blases`].
 fn create_blas_instances(
     device: &maligog::Device,
-    scene: &gltf::Scene,
     blases: &[maligog::BottomAccelerationStructure],
+    flipped_blases: &[Option<maligog::BottomAccelerationStructure>],
+    deformed_meshes: &[DeformedMesh],
+    instance_infos: &[InstanceInfo],
+    root_transform: &glam::Mat4,
 ) -> Vec<maligog::BLASInstance> {
-    let mut instance_offset = 0;
-    let instances = scene
-        .nodes()
-        .map(|node| {
-            process_node(
+    instance_infos
+        .iter()
+        .map(|info| {
+            let transform = match info.visible {
+                true => *root_transform * info.transform,
+                false => glam::Mat4::ZERO,
+            };
+            let deformed = deformed_meshes
+                .iter()
+                .find(|d| Some(d.node_index) == info.node_index && d.mesh_index == info.mesh_index);
+            let blas = match (deformed, mirrors(&transform)) {
+                (Some(deformed), true) => deformed.flipped_blas.as_ref().unwrap(),
+                (Some(deformed), false) => &deformed.blas,
+                (None, true) => flipped_blases[info.mesh_index].as_ref().unwrap(),
+                (None, false) => &blases[info.mesh_index],
+            };
+            let mut instance = maligog::BLASInstance::new(
                 device,
-                &node,
-                blases,
-                &mut instance_offset,
-                &glam::Mat4::IDENTITY,
-            )
+                blas,
+                &transform,
+                info.custom_index,
+                info.sbt_offset,
+            );
+            instance.build();
+            instance
         })
-        .flatten()
+        .collect()
+}
+
+fn gather_instance_transforms(blas_instances: &[maligog::BLASInstance]) -> Vec<u8> {
+    let mut transforms = Vec::with_capacity(blas_instances.len());
+    for instance in blas_instances {
+        transforms.push(instance.transform().to_owned());
+    }
+    bytemuck::cast_slice(&transforms).to_vec()
+}
+
+fn create_transform_buffer(
+    device: &maligog::Device,
+    blas_instances: &[maligog::BLASInstance],
+) -> maligog::Buffer {
+    // replaced whenever instances move
+    device.create_buffer_init(
+        Some("transform buffer"),
+        gather_instance_transforms(blas_instances),
+        maligog::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
+            | maligog::BufferUsageFlags::STORAGE_BUFFER,
+        maligog::MemoryLocation::CpuToGpu,
+    )
+}
+
+fn create_joint_matrix_buffer(
+    device: &maligog::Device,
+    joint_matrices: &[glam::Mat4],
+) -> Option<maligog::Buffer> {
+    match joint_matrices.len() {
+        0 => None,
+        // replaced on every pose update
+        _ => Some(device.create_buffer_init(
+            Some("joint matrix buffer"),
+            bytemuck::cast_slice(joint_matrices),
+            maligog::BufferUsageFlags::STORAGE_BUFFER,
+            maligog::MemoryLocation::CpuToGpu,
+        )),
+    }
+}
+
+fn create_light_buffer(device: &maligog::Device, lights: &[Light]) -> Option<maligog::Buffer> {
+    match lights.len() {
+        0 => None,
+        // replaced whenever lights move
+        _ => Some(device.create_buffer_init(
+            Some("light buffer"),
+            bytemuck::cast_slice(&light::gather_gpu_lights(lights)),
+            maligog::BufferUsageFlags::STORAGE_BUFFER,
+            maligog::MemoryLocation::CpuToGpu,
+        )),
+    }
+}
+
+fn create_geometry_buffer(
+    device: &maligog::Device,
+    instance_infos: &[InstanceInfo],
+    mesh_infos: &[MeshInfo],
+) -> maligog::Buffer {
+    let geometry_infos = gpu::gather_geometry_infos(instance_infos, mesh_infos);
+    device.create_buffer_init(
+        Some("geometry buffer"),
+        bytemuck::cast_slice(&geometry_infos),
+        maligog::BufferUsageFlags::STORAGE_BUFFER,
+        maligog::MemoryLocation::GpuOnly,
+    )
+}
+
+fn create_primitive_buffer(device: &maligog::Device, mesh_infos: &[MeshInfo]) -> maligog::Buffer {
+    let gpu_primitive_infos = mesh_infos
+        .iter()
+        .flat_map(|m| m.primitive_infos.iter().map(GpuPrimitiveInfo::from))
         .collect::<Vec<_>>();
-    instances
+    device.create_buffer_init(
+        Some("primitive buffer"),
+        bytemuck::cast_slice(&gpu_primitive_infos),
+        maligog::BufferUsageFlags::STORAGE_BUFFER,
+        maligog::MemoryLocation::GpuOnly,
+    )
+}
+
+fn create_instance_attribute_buffer(
+    device: &maligog::Device,
+    instance_infos: &[InstanceInfo],
+    instancing: &[Option<NodeInstancing>],
+    attribute_names: &[String],
+) -> Option<maligog::Buffer> {
+    if attribute_names.is_empty() {
+        return None;
+    }
+    let attributes =
+        instancing::gather_instance_attributes(instance_infos, instancing, attribute_names);
+    Some(device.create_buffer_init(
+        Some("instance attribute buffer"),
+        bytemuck::cast_slice(&attributes),
+        maligog::BufferUsageFlags::STORAGE_BUFFER,
+        maligog::MemoryLocation::GpuOnly,
+    ))
+}
+
+fn create_instance_bounds_buffer(
+    device: &maligog::Device,
+    instance_bounds: &[Aabb],
+) -> Option<maligog::Buffer> {
+    match instance_bounds.len() {
+        0 => None,
+        // replaced whenever instances move
+        _ => Some(
+            device.create_buffer_init(
+                Some("instance bounds buffer"),
+                bytemuck::cast_slice(
+                    &instance_bounds
+                        .iter()
+                        .map(GpuAabb::from)
+                        .collect::<Vec<_>>(),
+                ),
+                maligog::BufferUsageFlags::STORAGE_BUFFER,
+                maligog::MemoryLocation::CpuToGpu,
+            ),
+        ),
+    }
+}
+
+fn create_emissive_triangle_buffer(
+    device: &maligog::Device,
+    emissive_triangles: &[EmissiveTriangle],
+) -> Option<maligog::Buffer> {
+    match emissive_triangles.len() {
+        0 => None,
+        // replaced whenever instances move
+        _ => Some(device.create_buffer_init(
+            Some("emissive triangle buffer"),
+            bytemuck::cast_slice(&emissive::gather_gpu_emissive_triangles(emissive_triangles)),
+            maligog::BufferUsageFlags::STORAGE_BUFFER,
+            maligog::MemoryLocation::CpuToGpu,
+        )),
+    }
+}
+
+/// Raw positions padded to four 16-bit components, for positions stored as
+/// normalized 16-bit integers. Other types have no acceleration structure
+/// vertex format, or rely on the node transform to dequantize, and are
+/// converted to floats instead. So are unsigned ones, unless `unorm16` says
+/// the device builds acceleration structures from them.
+fn quantize_positions(
+    accessor: &gltf::Accessor,
+    buffers: &[gltf::buffer::Data],
+    unorm16: bool,
+) -> Option<(PositionFormat, Vec<u8>)> {
+    let format = match (accessor.data_type(), accessor.normalized()) {
+        (gltf::accessor::DataType::I16, true) => PositionFormat::Snorm16,
+        (gltf::accessor::DataType::U16, true) if unorm16 => PositionFormat::Unorm16,
+        _ => return None,
+    };
+    accessor::read_raw_elements(accessor, buffers, 8).map(|data| (format, data))
 }
 
 fn process_meshes(
     device: &maligog::Device,
-    gltf_meshes: gltf::iter::Meshes,
+    doc: &gltf::Document,
+    raw_json: &RawJson,
     buffers: &[gltf::buffer::Data],
-) -> MeshData {
+    options: &LoadOptions,
+) -> Result<MeshData, DracoError> {
+    let draco_decoder: &dyn DracoDecoder = match &options.draco_decoder {
+        Some(decoder) => decoder.as_ref(),
+        None => &SequentialDracoDecoder,
+    };
     let mut index_data: Vec<u8> = Vec::new();
     let mut vertex_data: Vec<u8> = Vec::new();
     let mut color_data: Vec<u8> = Vec::new();
     let mut tex_coord_data: Vec<u8> = Vec::new();
+    let mut quantized_vertex_data: Vec<u8> = Vec::new();
     let mut mesh_infos: Vec<MeshInfo> = Vec::new();
-    for mesh in gltf_meshes {
+    let mut geometry = MeshGeometry::default();
+    for mesh in doc.meshes() {
         let mut primitive_infos = Vec::new();
+        let mut morph_targets = Vec::new();
         for primitive in mesh.primitives() {
             let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
-            let index_iter = reader.read_indices().unwrap().into_u32();
-            let vertex_iter = reader.read_positions().unwrap();
-            let indices = index_iter.collect::<Vec<_>>();
-            let vertices = vertex_iter.collect::<Vec<_>>();
-            let has_colors = reader.read_colors(0).is_some();
-            let has_tex_coords = reader.read_tex_coords(0).is_some();
-            let colors = match reader.read_colors(0).map(|i| i.into_rgba_f32()) {
-                Some(iter) => iter.collect::<Vec<_>>(),
-                None => vec![],
+            let draco = draco::decode_primitive(
+                &primitive,
+                mesh.index(),
+                raw_json,
+                buffers,
+                doc,
+                draco_decoder,
+            )?;
+            let indices = match &draco {
+                Some(draco) => draco.indices.clone(),
+                None => reader
+                    .read_indices()
+                    .unwrap()
+                    .into_u32()
+                    .collect::<Vec<_>>(),
             };
-            let tex_coords = match reader.read_tex_coords(0).map(|i| i.into_f32()) {
-                Some(iter) => iter.collect::<Vec<_>>(),
-                None => vec![],
+            // positions and texture coordinates go through the generic accessor
+            // reader, which accepts the integer types of KHR_mesh_quantization
+            let position_accessor = primitive.get(&gltf::Semantic::Positions).unwrap();
+            let vertices = match &draco {
+                Some(draco) => draco
+                    .vectors::<3>("POSITION", 0.0)
+                    .ok_or_else(|| DracoError::MissingAttribute("POSITION".to_owned()))?,
+                None => accessor::read_vectors::<3>(&position_accessor, buffers),
             };
+            let quantized_positions = match options.quantized_positions && draco.is_none() {
+                true => {
+                    quantize_positions(&position_accessor, buffers, options.unorm16_vertex_format)
+                }
+                false => None,
+            };
+            let colors = match &draco {
+                Some(draco) => draco.vectors::<4>("COLOR_0", 1.0),
+                None => reader
+                    .read_colors(0)
+                    .map(|i| i.into_rgba_f32().collect::<Vec<_>>()),
+            };
+            let has_colors = colors.is_some();
+            let colors = colors.unwrap_or_default();
+            let tex_coords = match &draco {
+                Some(draco) => draco.vectors::<2>("TEXCOORD_0", 0.0),
+                None => primitive
+                    .get(&gltf::Semantic::TexCoords(0))
+                    .map(|accessor| accessor::read_vectors::<2>(&accessor, buffers)),
+            };
+            let has_tex_coords = tex_coords.is_some();
+            let tex_coords = tex_coords.unwrap_or_default();
+            let joints = match &draco {
+                Some(draco) => draco.vectors::<4>("JOINTS_0", 0.0).map(|joints| {
+                    joints
+                        .iter()
+                        .map(|j| [j[0] as u32, j[1] as u32, j[2] as u32, j[3] as u32])
+                        .collect::<Vec<_>>()
+                }),
+                None => reader.read_joints(0).map(|i| {
+                    i.into_u16()
+                        .map(|j| [j[0] as u32, j[1] as u32, j[2] as u32, j[3] as u32])
+                        .collect::<Vec<_>>()
+                }),
+            };
+            let weights = match &draco {
+                Some(draco) => draco.vectors::<4>("WEIGHTS_0", 0.0),
+                None => reader
+                    .read_weights(0)
+                    .map(|i| i.into_f32().collect::<Vec<_>>()),
+            };
+            morph_targets.push(morph::read_morph_targets(&primitive, buffers));
             let material_index = match primitive.material().index() {
                 Some(i) => i as u64 + 1,
                 None => 0,
             };
+            let (position_format, quantized_vertex_offset) = match &quantized_positions {
+                Some((format, _)) => (*format, Some(quantized_vertex_data.len() as u64)),
+                None => (PositionFormat::Float, None),
+            };
+            let bounds = bounds::accessor_bounds(&position_accessor).unwrap_or_else(|| {
+                Aabb::from_points(vertices.iter().map(|v| glam::Vec3::from(*v)))
+            });
             primitive_infos.push(PrimitiveInfo {
+                bounds,
                 index_offset: index_data.len() as u64,
                 vertex_offset: vertex_data.len() as u64,
                 index_count: indices.len() as u64,
                 vertex_count: vertices.len() as u64,
+                first_vertex: geometry.positions.len() as u64,
+                position_format,
+                position_scale: position_format.scale(),
+                quantized_vertex_offset,
                 material_index,
                 color_offset: match has_colors {
                     true => Some(color_data.len() as u64),
@@ -287,15 +743,35 @@ fn process_meshes(
                     true => Some(tex_coord_data.len() as u64),
                     false => None,
                 },
+                joints_offset: joints.as_ref().map(|_| (geometry.joints.len() * 16) as u64),
+                weights_offset: weights
+                    .as_ref()
+                    .map(|_| (geometry.weights.len() * 16) as u64),
+                alpha_mode: primitive.material().alpha_mode(),
             });
             index_data.extend_from_slice(&bytemuck::cast_slice(&indices));
-            vertex_data.extend_from_slice(&bytemuck::cast_slice(&vertices));
+            match quantized_positions {
+                Some((_, quantized)) => quantized_vertex_data.extend(quantized),
+                None => vertex_data.extend_from_slice(bytemuck::cast_slice(&vertices)),
+            }
             color_data.extend_from_slice(&bytemuck::cast_slice(&colors));
             tex_coord_data.extend_from_slice(&bytemuck::cast_slice(&tex_coords));
+            geometry.colors.extend(colors);
+            geometry.tex_coords.extend(tex_coords);
+            geometry.indices.extend(indices);
+            geometry.positions.extend(vertices);
+            geometry.joints.extend(joints.into_iter().flatten());
+            geometry.weights.extend(weights.into_iter().flatten());
         }
         mesh_infos.push(MeshInfo {
+            bounds: primitive_infos
+                .iter()
+                .fold(Aabb::empty(), |bounds, p| bounds.union(&p.bounds)),
             name: mesh.name().map(|s| s.to_owned()),
             primitive_infos,
+            morph_targets,
+            weights: mesh.weights().map(|w| w.to_vec()).unwrap_or_default(),
+            extras: extras::load(raw_json, "meshes", mesh.index()),
         });
     }
     let index_buffer = device.create_buffer_init(
@@ -314,6 +790,16 @@ fn process_meshes(
             | maligog::BufferUsageFlags::STORAGE_BUFFER,
         maligog::MemoryLocation::GpuOnly,
     );
+    let quantized_vertex_buffer = match quantized_vertex_data.is_empty() {
+        false => Some(device.create_buffer_init(
+            Some("quantized vertex buffer"),
+            bytemuck::cast_slice(&quantized_vertex_data),
+            maligog::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
+                | maligog::BufferUsageFlags::STORAGE_BUFFER,
+            maligog::MemoryLocation::GpuOnly,
+        )),
+        true => None,
+    };
     let color_buffer = match color_data.len() != 0 {
         true => Some(device.create_buffer_init(
             Some("vertex color buffer"),
@@ -334,33 +820,97 @@ fn process_meshes(
         )),
         false => None,
     };
+    let joint_buffer = match geometry.joints.is_empty() {
+        false => Some(device.create_buffer_init(
+            Some("joint buffer"),
+            bytemuck::cast_slice(&geometry.joints),
+            maligog::BufferUsageFlags::STORAGE_BUFFER,
+            maligog::MemoryLocation::GpuOnly,
+        )),
+        true => None,
+    };
+    let weight_buffer = match geometry.weights.is_empty() {
+        false => Some(device.create_buffer_init(
+            Some("weight buffer"),
+            bytemuck::cast_slice(&geometry.weights),
+            maligog::BufferUsageFlags::STORAGE_BUFFER,
+            maligog::MemoryLocation::GpuOnly,
+        )),
+        true => None,
+    };
+    let primitive_buffer = create_primitive_buffer(device, &mesh_infos);
 
-    MeshData {
+    Ok(MeshData {
         index_buffer,
         vertex_buffer,
+        quantized_vertex_buffer,
         mesh_infos,
         color_buffer,
         tex_coord_buffer,
-    }
+        joint_buffer,
+        weight_buffer,
+        primitive_buffer,
+        geometry,
+    })
 }
 
-fn create_blases(
+/// Builds the BLAS of `mesh`. `deformed_vertex_buffer` replaces the mesh's
+/// range of the scene vertex buffer, see [`deform::DeformedMesh`].
+/// `flip_facing` builds it from a copy of the indices with the winding of
+/// every triangle reversed, for instances whose transform mirrors.
+fn create_blas(
     device: &maligog::Device,
     mesh_data: &MeshData,
-) -> Vec<maligog::BottomAccelerationStructure> {
-    let mut blases = Vec::new();
-    for mesh in &mesh_data.mesh_infos {
-        let mut triangle_geometries = Vec::new();
-        for primitive in &mesh.primitive_infos {
-            let index_buffer_view = maligog::IndexBufferView {
-                buffer_view: maligog::BufferView {
+    mesh: &MeshInfo,
+    deformed_vertex_buffer: Option<&maligog::Buffer>,
+    flip_facing: bool,
+) -> maligog::BottomAccelerationStructure {
+    let first_vertex = mesh.primitive_infos.first().map_or(0, |p| p.first_vertex);
+    let first_index_offset = mesh.primitive_infos.first().map_or(0, |p| p.index_offset);
+    let flipped_index_buffer = match flip_facing {
+        true => Some(create_flipped_index_buffer(device, mesh_data, mesh)),
+        false => None,
+    };
+    let mut triangle_geometries = Vec::new();
+    for primitive in &mesh.primitive_infos {
+        let index_buffer_view = maligog::IndexBufferView {
+            buffer_view: match &flipped_index_buffer {
+                Some(buffer) => maligog::BufferView {
+                    buffer: buffer.clone(),
+                    offset: primitive.index_offset - first_index_offset,
+                },
+                None => maligog::BufferView {
                     buffer: mesh_data.index_buffer.clone(),
                     offset: primitive.index_offset,
                 },
-                index_type: maligog::IndexType::UINT32,
-                count: primitive.index_count as u32,
-            };
-            let vertex_buffer_view = maligog::VertexBufferView {
+            },
+            index_type: maligog::IndexType::UINT32,
+            count: primitive.index_count as u32,
+        };
+        let vertex_buffer_view = match (deformed_vertex_buffer, primitive.quantized_vertex_offset) {
+            // deformed positions are always floats
+            (Some(buffer), _) => maligog::VertexBufferView {
+                buffer_view: maligog::BufferView {
+                    buffer: buffer.clone(),
+                    offset: (primitive.first_vertex - first_vertex) * 12,
+                },
+                format: maligog::Format::R32G32B32_SFLOAT,
+                stride: std::mem::size_of::<f32>() as u64 * 3,
+                count: primitive.vertex_count as u32,
+            },
+            (None, Some(offset)) => maligog::VertexBufferView {
+                buffer_view: maligog::BufferView {
+                    buffer: mesh_data.quantized_vertex_buffer.clone().unwrap(),
+                    offset,
+                },
+                format: match primitive.position_format {
+                    PositionFormat::Unorm16 => maligog::Format::R16G16B16A16_UNORM,
+                    _ => maligog::Format::R16G16B16A16_SNORM,
+                },
+                stride: std::mem::size_of::<u16>() as u64 * 4,
+                count: primitive.vertex_count as u32,
+            },
+            (None, None) => maligog::VertexBufferView {
                 buffer_view: maligog::BufferView {
                     buffer: mesh_data.vertex_buffer.clone(),
                     offset: primitive.vertex_offset,
@@ -368,21 +918,103 @@ fn create_blases(
                 format: maligog::Format::R32G32B32_SFLOAT,
                 stride: std::mem::size_of::<f32>() as u64 * 3,
                 count: primitive.vertex_count as u32,
-            };
+            },
+        };
 
-            triangle_geometries.push(maligog::TriangleGeometry::new(
-                &index_buffer_view,
-                &vertex_buffer_view,
-                None,
-            ))
-        }
-        blases.push(device.create_bottom_level_acceleration_structure(None, &triangle_geometries));
+        triangle_geometries.push(maligog::TriangleGeometry::new(
+            &index_buffer_view,
+            &vertex_buffer_view,
+            None,
+        ))
     }
+    device.create_bottom_level_acceleration_structure(mesh.name.as_deref(), &triangle_geometries)
+}
 
-    blases
+/// The indices of all primitives of `mesh`, with the second and third index
+/// of each triangle swapped.
+fn create_flipped_index_buffer(
+    device: &maligog::Device,
+    mesh_data: &MeshData,
+    mesh: &MeshInfo,
+) -> maligog::Buffer {
+    let mut indices = Vec::new();
+    for primitive in &mesh.primitive_infos {
+        let start = (primitive.index_offset / 4) as usize;
+        indices.extend_from_slice(
+            &mesh_data.geometry.indices[start..start + primitive.index_count as usize],
+        );
+    }
+    for triangle in indices.chunks_exact_mut(3) {
+        triangle.swap(1, 2);
+    }
+    device.create_buffer_init(
+        Some("flipped index buffer"),
+        bytemuck::cast_slice(&indices),
+        maligog::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR,
+        maligog::MemoryLocation::GpuOnly,
+    )
+}
+
+fn create_blases(
+    device: &maligog::Device,
+    mesh_data: &MeshData,
+) -> Vec<maligog::BottomAccelerationStructure> {
+    mesh_data
+        .mesh_infos
+        .iter()
+        .map(|mesh| create_blas(device, mesh_data, mesh, None, false))
+        .collect()
+}
+
+/// Whether `transform` mirrors, which reverses the winding of triangles.
+fn mirrors(transform: &glam::Mat4) -> bool {
+    transform.determinant() < 0.0
+}
+
+/// Builds the flipped BLASes that the visible instances with a mirroring
+/// transform need and don't have yet.
+fn create_flipped_blases(
+    device: &maligog::Device,
+    mesh_data: &MeshData,
+    flipped_blases: &mut [Option<maligog::BottomAccelerationStructure>],
+    deformed_meshes: &mut [DeformedMesh],
+    instance_infos: &[InstanceInfo],
+    root_transform: &glam::Mat4,
+) {
+    for info in instance_infos {
+        if !info.visible || !mirrors(&(*root_transform * info.transform)) {
+            continue;
+        }
+        let mesh = &mesh_data.mesh_infos[info.mesh_index];
+        match deformed_meshes
+            .iter_mut()
+            .find(|d| Some(d.node_index) == info.node_index && d.mesh_index == info.mesh_index)
+        {
+            Some(deformed) => {
+                if deformed.flipped_blas.is_none() {
+                    deformed.flipped_blas = Some(create_blas(
+                        device,
+                        mesh_data,
+                        mesh,
+                        Some(&deformed.vertex_buffer),
+                        true,
+                    ));
+                }
+            }
+            None => {
+                if flipped_blases[info.mesh_index].is_none() {
+                    flipped_blases[info.mesh_index] =
+                        Some(create_blas(device, mesh_data, mesh, None, true));
+                }
+            }
+        }
+    }
 }
 
-fn gather_material_infos(gltf_materials: gltf::iter::Materials) -> Vec<MaterialInfo> {
+fn gather_material_infos(
+    gltf_materials: gltf::iter::Materials,
+    raw_json: &RawJson,
+) -> Vec<MaterialInfo> {
     let mut material_infos = Vec::new();
     material_infos.push(MaterialInfo {
         base_color_factor: glam::Vec4::new(1.0, 1.0, 1.0, 1.0),
@@ -390,6 +1022,12 @@ fn gather_material_infos(gltf_materials: gltf::iter::Materials) -> Vec<MaterialI
         metallic_roughness_texture: None,
         metallic_factor: 1.0,
         roughness_factor: 1.0,
+        alpha_mode: gltf::material::AlphaMode::Opaque,
+        alpha_cutoff: 0.5,
+        emissive_factor: glam::Vec3::ZERO,
+        emissive_texture: None,
+        packed_texture: None,
+        extras: None,
     });
     for m in gltf_materials {
         let metallic_roughness = m.pbr_metallic_roughness();
@@ -411,6 +1049,13 @@ fn gather_material_infos(gltf_materials: gltf::iter::Materials) -> Vec<MaterialI
                     },
                     image_index: t.texture().source().index() as u32,
                 });
+        let emissive_texture = m.emissive_texture().map(|t| Texture {
+            sampler_index: match t.texture().sampler().index() {
+                Some(i) => i as u32 + 1,
+                None => 0,
+            },
+            image_index: t.texture().source().index() as u32,
+        });
         let metallic_factor = metallic_roughness.metallic_factor();
         let roughness_factor = metallic_roughness.roughness_factor();
         material_infos.push(MaterialInfo {
@@ -419,65 +1064,261 @@ fn gather_material_infos(gltf_materials: gltf::iter::Materials) -> Vec<MaterialI
             metallic_roughness_texture,
             metallic_factor,
             roughness_factor,
+            alpha_mode: m.alpha_mode(),
+            alpha_cutoff: m.alpha_cutoff().unwrap_or(0.5),
+            emissive_factor: glam::Vec3::from(m.emissive_factor()),
+            emissive_texture,
+            packed_texture: None,
+            extras: extras::load(raw_json, "materials", m.index().unwrap()),
         });
     }
     material_infos
 }
 
 impl Scene {
+    /// Loads with default options, panicking when loading fails.
     pub fn from_file<I: AsRef<Path>>(
         name: Option<&str>,
         device: &maligog::Device,
         path: I,
     ) -> Self {
-        let (doc, gltf_buffers, gltf_images) = gltf::import(path).unwrap();
-        let scene = doc.default_scene().unwrap();
+        Self::from_file_with_options(name, device, path, &LoadOptions::default())
+            .unwrap_or_else(|error| panic!("failed to load glTF: {}", error))
+    }
 
-        let mesh_data = process_meshes(device, doc.meshes(), &gltf_buffers);
+    pub fn from_file_with_options<I: AsRef<Path>>(
+        name: Option<&str>,
+        device: &maligog::Device,
+        path: I,
+        options: &LoadOptions,
+    ) -> Result<Self, LoadError> {
+        let import::Import {
+            doc,
+            buffers: mut gltf_buffers,
+            images: gltf_images,
+            raw_json,
+        } = import::import(path.as_ref())?;
+        let support_report = SupportReport::new(&doc);
+        let unsupported = support_report
+            .unsupported_required()
+            .map(|e| e.name.clone())
+            .collect::<Vec<_>>();
+        if !unsupported.is_empty() {
+            match options.best_effort {
+                true => log::warn!("ignoring required extensions {}", unsupported.join(", ")),
+                false => return Err(LoadError::UnsupportedExtensions(unsupported)),
+            }
+        }
+        meshopt::decode_buffer_views(&doc, &raw_json, &mut gltf_buffers)?;
+        let scene = doc.default_scene().ok_or(LoadError::NoDefaultScene)?;
+
+        let mesh_data = process_meshes(device, &doc, &raw_json, &gltf_buffers, options)?;
 
         log::debug!("loading images");
-        let images = create_device_images(device, &gltf_images);
+        let mut images = create_device_images(device, &gltf_images);
+        let packed_textures = options.texture_packing.as_ref().map(|spec| {
+            log::debug!("repacking textures");
+            let packed =
+                repack::repack_textures(spec, doc.materials(), &gltf_images, images.len() as u32);
+            images.extend(create_packed_device_images(device, &packed.images));
+            packed.material_textures
+        });
         log::debug!("loading meshes");
         let blases = create_blases(device, &mesh_data);
         log::debug!("loading samplers");
-        let samplers = create_samlers(device, doc.samplers());
+        let sampler_infos = std::iter::once(SamplerInfo::gltf_default())
+            .chain(doc.samplers().map(|sampler| SamplerInfo::new(&sampler)))
+            .collect::<Vec<_>>();
+        let samplers = create_samlers(device, doc.samplers(), &sampler_infos);
 
-        let mut blas_instances =
-            create_blas_instances(device, doc.default_scene().as_ref().unwrap(), &blases);
-        for instance in blas_instances.as_mut_slice() {
-            instance.build();
+        let mut material_infos = gather_material_infos(doc.materials(), &raw_json);
+        if let Some(packed_textures) = packed_textures {
+            for (info, packed_texture) in material_infos.iter_mut().zip(packed_textures) {
+                info.packed_texture = packed_texture;
+            }
+        }
+        let policy = options
+            .instance_policy
+            .clone()
+            .unwrap_or_else(|| Arc::new(MeshIndexPolicy));
+        let instancing = instancing::load_node_instancing(&doc, &raw_json, &gltf_buffers);
+        let instance_attribute_names = instancing::attribute_names(&instancing);
+        let material_variants = MaterialVariants::load(&doc, &raw_json);
+        let node_extras = extras::load_all(&raw_json, "nodes", doc.nodes().len());
+        let instance_infos = gather_instance_infos(
+            &InstanceBuildContext {
+                mesh_infos: &mesh_data.mesh_infos,
+                material_infos: &material_infos,
+                policy: policy.as_ref(),
+                instancing: &instancing,
+                node_extras: &node_extras,
+            },
+            &scene,
+        );
+        if !gpu::custom_indices_fit(&instance_infos) {
+            return Err(LoadError::TooManyGeometries);
         }
+        let scene_graph = SceneGraph::new(&doc, &scene, node_extras);
+        let skins = skin::load_skins(doc.skins(), &gltf_buffers);
+        let (mut deformed_meshes, joint_matrices) =
+            deform::create_deformed_meshes(device, &mesh_data, &skins, &scene_graph);
+        let joint_matrix_buffer = create_joint_matrix_buffer(device, &joint_matrices);
+
+        let conversion = options.axis_conversion.matrix();
+        let normalization = match options.normalize_extent {
+            Some(extent) => bounds::normalization(
+                &bounds::gather_instance_bounds(
+                    &instance_infos,
+                    &mesh_data.mesh_infos,
+                    &deformed_meshes,
+                    &conversion,
+                ),
+                extent,
+            ),
+            None => glam::Mat4::IDENTITY,
+        };
+        let root_transform = normalization * conversion;
+        let mut flipped_blases = vec![None; blases.len()];
+        create_flipped_blases(
+            device,
+            &mesh_data,
+            &mut flipped_blases,
+            &mut deformed_meshes,
+            &instance_infos,
+            &root_transform,
+        );
+        let blas_instances = create_blas_instances(
+            device,
+            &blases,
+            &flipped_blases,
+            &deformed_meshes,
+            &instance_infos,
+            &root_transform,
+        );
         let instance_geometry = maligog::InstanceGeometry::new(&device, blas_instances.as_slice());
         let tlas =
             device.create_top_level_acceleration_structure(scene.name(), &[instance_geometry]);
         let load_time = std::time::Instant::now();
 
-        let mut transforms = Vec::with_capacity(blas_instances.len());
-        for instance in blas_instances {
-            transforms.push(instance.transform().to_owned());
+        let transform_buffer = create_transform_buffer(device, &blas_instances);
+
+        let mut scene_graph = scene_graph;
+        scene_graph.link_instances(&instance_infos);
+        let mut cameras = camera::load_cameras(&doc, &scene_graph);
+        for camera in &mut cameras {
+            camera.world_transform = root_transform * camera.world_transform;
         }
-        let transform_buffer = device.create_buffer_init(
-            Some("transform buffer"),
-            bytemuck::cast_slice(&transforms),
-            maligog::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
-                | maligog::BufferUsageFlags::STORAGE_BUFFER,
+        let mut lights = light::load_lights(&doc, &scene_graph);
+        for light in &mut lights {
+            light.world_transform = root_transform * light.world_transform;
+            light.range = light
+                .range
+                .map(|range| range * axis::uniform_scale(&root_transform));
+        }
+        let light_buffer = create_light_buffer(device, &lights);
+        let animations = animation::load_animations(doc.animations(), &gltf_buffers);
+
+        let geometry_buffer =
+            create_geometry_buffer(device, &instance_infos, &mesh_data.mesh_infos);
+        let attribute_buffer = create_instance_attribute_buffer(
+            device,
+            &instance_infos,
+            &instancing,
+            &instance_attribute_names,
+        );
+
+        let gpu_material_infos = material_infos
+            .iter()
+            .map(GpuMaterialInfo::from)
+            .collect::<Vec<_>>();
+        let material_buffer = device.create_buffer_init(
+            Some("material buffer"),
+            bytemuck::cast_slice(&gpu_material_infos),
+            maligog::BufferUsageFlags::STORAGE_BUFFER,
+            maligog::MemoryLocation::GpuOnly,
+        );
+        // storage buffer bindings must always be written, so absent attribute
+        // streams are backed by a small placeholder
+        let empty_buffer = device.create_buffer_init(
+            Some("empty buffer"),
+            bytemuck::cast_slice(&[0u32; 4]),
+            maligog::BufferUsageFlags::STORAGE_BUFFER,
             maligog::MemoryLocation::GpuOnly,
         );
 
-        let material_infos = gather_material_infos(doc.materials());
+        log::debug!("gathering emissive triangles");
+        let material_radiance = emissive::material_radiance(&material_infos, &gltf_images);
+        let emissive_triangles = emissive::gather_emissive_triangles(
+            &instance_infos,
+            &mesh_data.mesh_infos,
+            &mesh_data.geometry,
+            &material_radiance,
+            &root_transform,
+        );
+        let emissive_triangle_buffer = create_emissive_triangle_buffer(device, &emissive_triangles);
+        let instance_bounds = bounds::gather_instance_bounds(
+            &instance_infos,
+            &mesh_data.mesh_infos,
+            &deformed_meshes,
+            &root_transform,
+        );
+        let bounds_buffer = create_instance_bounds_buffer(device, &instance_bounds);
+        let mesh_bvhs = mesh_data
+            .mesh_infos
+            .iter()
+            .map(|mesh| Bvh::for_mesh(mesh, &mesh_data.geometry))
+            .collect();
 
-        Self {
+        Ok(Self {
             mesh_data,
             images,
             tlas,
             samplers,
+            sampler_infos,
             doc,
             load_time,
-            instance_data: InstanceData { transform_buffer },
+            instance_data: InstanceData {
+                transform_buffer,
+                geometry_buffer,
+                attribute_buffer,
+                bounds_buffer,
+            },
             material_infos,
-        }
+            material_buffer,
+            empty_buffer,
+            instance_infos,
+            blases,
+            flipped_blases,
+            scene_graph,
+            animations,
+            pending_instance_update: None,
+            changed_instances: BTreeSet::new(),
+            skins,
+            deformed_meshes,
+            joint_matrix_buffer,
+            cameras,
+            lights,
+            light_buffer,
+            material_radiance,
+            emissive_triangles,
+            emissive_triangle_buffer,
+            instancing,
+            instance_attribute_names,
+            material_variants,
+            support_report,
+            root_transform,
+            normalization,
+            instance_bounds,
+            mesh_bvhs,
+        })
     }
 
+    /// The TLAS of the scene's instances. Instances whose transform mirrors,
+    /// through [`LoadOptions::axis_conversion`] or a node with a negative
+    /// scale, reference a BLAS with the second and third index of every
+    /// triangle swapped, so hit kinds follow the winding seen in world space.
+    /// Hit shaders reading [`index_buffer`](Self::index_buffer) swap the two
+    /// barycentrics when `determinant(mat3(gl_ObjectToWorldEXT))` is negative.
     pub fn tlas(&self) -> &maligog::TopAccelerationStructure {
         &self.tlas
     }
@@ -500,6 +1341,19 @@ impl Scene {
         }
     }
 
+    /// Positions of primitives kept quantized, see [`PositionFormat`]. `None`
+    /// unless [`LoadOptions::quantized_positions`] is set and some primitive
+    /// has normalized 16-bit positions.
+    pub fn quantized_vertex_buffer(&self) -> Option<maligog::BufferView> {
+        self.mesh_data
+            .quantized_vertex_buffer
+            .as_ref()
+            .map(|b| maligog::BufferView {
+                buffer: b.clone(),
+                offset: 0,
+            })
+    }
+
     pub fn color_buffer(&self) -> Option<maligog::BufferView> {
         self.mesh_data
             .color_buffer
@@ -535,6 +1389,14 @@ impl Scene {
         }
     }
 
+    /// Per-geometry records of every instance, see [`GpuGeometryInfo`].
+    pub fn geometry_buffer(&self) -> maligog::BufferView {
+        maligog::BufferView {
+            buffer: self.instance_data.geometry_buffer.clone(),
+            offset: 0,
+        }
+    }
+
     pub fn images(&self) -> &[maligog::Image] {
         &self.images
     }
@@ -542,6 +1404,582 @@ impl Scene {
     pub fn samplers(&self) -> &[maligog::Sampler] {
         &self.samplers
     }
+
+    /// Filters and address modes of each sampler in [`samplers`](Self::samplers).
+    pub fn sampler_infos(&self) -> &[SamplerInfo] {
+        &self.sampler_infos
+    }
+
+    /// Instances in TLAS order, with the mask and SBT offset chosen by
+    /// [`LoadOptions::instance_policy`].
+    pub fn instance_infos(&self) -> &[InstanceInfo] {
+        &self.instance_infos
+    }
+
+    pub fn skins(&self) -> &[Skin] {
+        &self.skins
+    }
+
+    /// `uvec4` joint indices, see [`PrimitiveInfo::joints_offset`].
+    pub fn joint_buffer(&self) -> Option<maligog::BufferView> {
+        self.mesh_data
+            .joint_buffer
+            .as_ref()
+            .map(|b| maligog::BufferView {
+                buffer: b.clone(),
+                offset: 0,
+            })
+    }
+
+    /// `vec4` joint weights, see [`PrimitiveInfo::weights_offset`].
+    pub fn weight_buffer(&self) -> Option<maligog::BufferView> {
+        self.mesh_data
+            .weight_buffer
+            .as_ref()
+            .map(|b| maligog::BufferView {
+                buffer: b.clone(),
+                offset: 0,
+            })
+    }
+
+    /// `mat4` joint matrices of the skinned mesh on `node_index`, as of the
+    /// last [`update_deformed_meshes`](Self::update_deformed_meshes).
+    pub fn joint_matrix_buffer(&self, node_index: usize) -> Option<maligog::BufferView> {
+        let deformed = self
+            .deformed_meshes
+            .iter()
+            .find(|d| d.node_index == node_index && d.skin_index.is_some())?;
+        self.joint_matrix_buffer
+            .as_ref()
+            .map(|b| maligog::BufferView {
+                buffer: b.clone(),
+                offset: (deformed.joint_offset * std::mem::size_of::<glam::Mat4>()) as u64,
+            })
+    }
+
+    /// Deformed positions of the mesh on `node_index`, as tightly packed
+    /// `vec3`s in primitive order. Unless positions are kept quantized, this
+    /// is laid out like the mesh's range of [`vertex_buffer`](Self::vertex_buffer).
+    pub fn deformed_vertex_buffer(&self, node_index: usize) -> Option<maligog::BufferView> {
+        self.deformed_meshes
+            .iter()
+            .find(|d| d.node_index == node_index)
+            .map(|d| maligog::BufferView {
+                buffer: d.vertex_buffer.clone(),
+                offset: 0,
+            })
+    }
+
+    /// Sets the morph target weights of a node. Takes effect on the next
+    /// [`update_deformed_meshes`](Self::update_deformed_meshes).
+    pub fn set_node_weights(&mut self, node_index: usize, weights: &[f32]) {
+        self.scene_graph.nodes[node_index].weights = weights.to_vec();
+    }
+
+    /// Cameras placed by nodes of the loaded scene, in depth-first node order.
+    pub fn cameras(&self) -> &[Camera] {
+        &self.cameras
+    }
+
+    /// `KHR_lights_punctual` lights placed by nodes of the loaded scene, in
+    /// depth-first node order.
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// [`GpuLight`]`[]` in the order of [`lights`](Self::lights), `None` when
+    /// the scene has no lights.
+    pub fn light_buffer(&self) -> Option<maligog::BufferView> {
+        self.light_buffer.as_ref().map(|b| maligog::BufferView {
+            buffer: b.clone(),
+            offset: 0,
+        })
+    }
+
+    /// Triangles of visible instances whose material emits light, as of the
+    /// last [`commit_instances`](Self::commit_instances). Deformed meshes
+    /// contribute their undeformed triangles.
+    pub fn emissive_triangles(&self) -> &[EmissiveTriangle] {
+        &self.emissive_triangles
+    }
+
+    /// [`GpuEmissiveTriangle`]`[]` in the order of
+    /// [`emissive_triangles`](Self::emissive_triangles), `None` when nothing emits.
+    pub fn emissive_triangle_buffer(&self) -> Option<maligog::BufferView> {
+        self.emissive_triangle_buffer
+            .as_ref()
+            .map(|b| maligog::BufferView {
+                buffer: b.clone(),
+                offset: 0,
+            })
+    }
+
+    /// World space bounds of every instance, in TLAS instance order, as of
+    /// the last [`commit_instances`](Self::commit_instances). Deformed meshes
+    /// use their current pose.
+    pub fn instance_bounds(&self) -> &[Aabb] {
+        &self.instance_bounds
+    }
+
+    /// [`GpuAabb`]`[]` holding [`instance_bounds`](Self::instance_bounds),
+    /// `None` when the scene has no instances.
+    pub fn instance_bounds_buffer(&self) -> Option<maligog::BufferView> {
+        self.instance_data
+            .bounds_buffer
+            .as_ref()
+            .map(|b| maligog::BufferView {
+                buffer: b.clone(),
+                offset: 0,
+            })
+    }
+
+    /// World space bounds of the visible instances.
+    pub fn bounds(&self) -> Aabb {
+        self.visible_instance_bounds()
+            .fold(Aabb::empty(), |bounds, b| bounds.union(b))
+    }
+
+    /// A sphere around the visible instances, centered on [`bounds`](Self::bounds).
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        bounds::bounding_sphere(&self.visible_instance_bounds().copied().collect::<Vec<_>>())
+    }
+
+    fn visible_instance_bounds(&self) -> impl Iterator<Item = &Aabb> {
+        self.instance_bounds
+            .iter()
+            .zip(&self.instance_infos)
+            .filter(|(_, info)| info.visible)
+            .map(|(bounds, _)| bounds)
+    }
+
+    /// Finds the closest triangle of a visible instance hit by the ray from
+    /// `origin` along `direction`, in the coordinates of the TLAS.
+    ///
+    /// Only CPU copies of the geometry are read: the triangles of each BLAS,
+    /// deformed meshes as of the last
+    /// [`update_deformed_meshes`](Self::update_deformed_meshes), placed with
+    /// the current instance transforms. Both faces are hit.
+    pub fn raycast(&self, origin: glam::Vec3, direction: glam::Vec3) -> Option<RayHit> {
+        let direction = direction.normalize();
+        let mut closest: Option<(usize, &[[f32; 3]], glam::Mat4, bvh::TriangleHit)> = None;
+        for (instance_index, info) in self.instance_infos.iter().enumerate() {
+            if !info.visible {
+                continue;
+            }
+            let mesh = &self.mesh_data.mesh_infos[info.mesh_index];
+            let (bvh, positions) =
+                match self.deformed_meshes.iter().find(|d| {
+                    Some(d.node_index) == info.node_index && d.mesh_index == info.mesh_index
+                }) {
+                    Some(deformed) => (&deformed.bvh, deformed.positions.as_slice()),
+                    None => (
+                        &self.mesh_bvhs[info.mesh_index],
+                        &self.mesh_data.geometry.positions[deform::mesh_vertex_range(mesh)],
+                    ),
+                };
+            // affine transforms keep distances along the ray, so hits in
+            // object space are at world space distances
+            let transform = self.root_transform * info.transform;
+            let inverse = transform.inverse();
+            let max_t = closest.map_or(f32::INFINITY, |(_, _, _, hit)| hit.t);
+            if let Some(hit) = bvh.intersect(
+                positions,
+                inverse.transform_point3(origin),
+                inverse.transform_vector3(direction),
+                max_t,
+            ) {
+                closest = Some((instance_index, positions, transform, hit));
+            }
+        }
+
+        let (instance_index, positions, transform, hit) = closest?;
+        let mesh_index = self.instance_infos[instance_index].mesh_index;
+        let primitive_index = hit.triangle.primitive_index as usize;
+        let triangle_index = hit.triangle.triangle_index as usize;
+        let primitive = &self.mesh_data.mesh_infos[mesh_index].primitive_infos[primitive_index];
+        let geometry = &self.mesh_data.geometry;
+        let indices = &geometry.primitive_indices(primitive)[triangle_index * 3..][..3];
+        let barycentrics = glam::Vec3::new(1.0 - hit.u - hit.v, hit.u, hit.v);
+        let [a, b, c] = hit
+            .triangle
+            .indices
+            .map(|i| transform.transform_point3(glam::Vec3::from(positions[i as usize])));
+        let tex_coord = geometry.primitive_tex_coords(primitive).map(|tex_coords| {
+            (0..3).fold(glam::Vec2::ZERO, |sum, i| {
+                sum + glam::Vec2::from(tex_coords[indices[i] as usize]) * barycentrics[i]
+            })
+        });
+        let color = geometry.primitive_colors(primitive).map(|colors| {
+            (0..3).fold(glam::Vec4::ZERO, |sum, i| {
+                sum + glam::Vec4::from(colors[indices[i] as usize]) * barycentrics[i]
+            })
+        });
+        Some(RayHit {
+            instance_index,
+            mesh_index,
+            primitive_index,
+            triangle_index,
+            barycentrics,
+            distance: hit.t,
+            position: origin + direction * hit.t,
+            normal: (b - a).cross(c - a).normalize(),
+            tex_coord,
+            color,
+        })
+    }
+
+    /// Names of the `EXT_mesh_gpu_instancing` custom attributes, in the order
+    /// they appear in [`instance_attribute_buffer`](Self::instance_attribute_buffer).
+    pub fn instance_attribute_names(&self) -> &[String] {
+        &self.instance_attribute_names
+    }
+
+    /// `vec4[]` holding the custom instancing attributes of every TLAS
+    /// instance, at `gl_InstanceID * instance_attribute_names().len() + attribute`.
+    /// Attributes with fewer than four components are zero padded, as are
+    /// instances without the attribute. `None` when no node has custom attributes.
+    pub fn instance_attribute_buffer(&self) -> Option<maligog::BufferView> {
+        self.instance_data
+            .attribute_buffer
+            .as_ref()
+            .map(|b| maligog::BufferView {
+                buffer: b.clone(),
+                offset: 0,
+            })
+    }
+
+    /// Transform from document coordinates to the coordinates of the TLAS,
+    /// cameras and lights, the [`normalization`](Self::normalization) after
+    /// [`LoadOptions::axis_conversion`]. Instance transforms are in document
+    /// coordinates, and its inverse maps world space positions back to them.
+    pub fn root_transform(&self) -> glam::Mat4 {
+        self.root_transform
+    }
+
+    /// The recentering and scaling applied for
+    /// [`LoadOptions::normalize_extent`], identity without it.
+    pub fn normalization(&self) -> glam::Mat4 {
+        self.normalization
+    }
+
+    /// The extensions the document uses and how each was handled.
+    pub fn support_report(&self) -> &SupportReport {
+        &self.support_report
+    }
+
+    /// Names of the `KHR_materials_variants` variants, indexed by variant.
+    pub fn material_variants(&self) -> &[String] {
+        &self.material_variants.names
+    }
+
+    /// The variant chosen with [`set_material_variant`](Self::set_material_variant),
+    /// `None` while primitives use their default materials.
+    pub fn active_material_variant(&self) -> Option<usize> {
+        self.material_variants.active
+    }
+
+    /// Switches every primitive to its material in `variant`, or back to its
+    /// default material for `None`, without reloading geometry.
+    ///
+    /// The primitive and geometry buffers are replaced, so descriptor sets
+    /// created from this scene have to be recreated. Instance masks and SBT
+    /// offsets chosen by the [`InstancePolicy`] are kept.
+    pub fn set_material_variant(&mut self, device: &maligog::Device, variant: Option<usize>) {
+        for (mesh_index, mesh) in self.mesh_data.mesh_infos.iter_mut().enumerate() {
+            for (primitive_index, primitive) in mesh.primitive_infos.iter_mut().enumerate() {
+                let material_index =
+                    self.material_variants
+                        .material_index(mesh_index, primitive_index, variant);
+                primitive.material_index = material_index;
+                primitive.alpha_mode = self.material_infos[material_index as usize].alpha_mode;
+            }
+        }
+        self.material_variants.active = variant;
+        self.mesh_data.primitive_buffer =
+            create_primitive_buffer(device, &self.mesh_data.mesh_infos);
+        // a rebuild rewrites the geometry buffer and the emissive triangles
+        self.mark_instances_dirty(InstanceUpdate::Rebuild);
+        self.commit_instances(device);
+    }
+
+    pub fn scene_graph(&self) -> &SceneGraph {
+        &self.scene_graph
+    }
+
+    pub fn animations(&self) -> &[Animation] {
+        &self.animations
+    }
+
+    /// Poses the scene as animation `animation_index` is at `time` seconds,
+    /// then commits the instances.
+    ///
+    /// maligog can neither refit a TLAS nor write into an existing buffer, so
+    /// every call rebuilds the TLAS from scratch and creates new transform,
+    /// light, emissive triangle and instance bounds buffers, plus new vertex
+    /// buffers and BLASes for deformed meshes that moved.
+    ///
+    /// Nodes not targeted by the animation keep their current transform.
+    /// Instances of nodes moved by the animation, directly or through an
+    /// ancestor, follow them and lose transforms set with
+    /// [`set_instance_transform`](Self::set_instance_transform). Other
+    /// instances are left alone.
+    pub fn animate(&mut self, device: &maligog::Device, animation_index: usize, time: f32) {
+        self.animations[animation_index].apply(time, &mut self.scene_graph);
+        self.scene_graph.update_world_transforms();
+        let moved = self
+            .scene_graph
+            .subtrees(self.animations[animation_index].transformed_nodes());
+        self.changed_instances.extend(follow_nodes(
+            &mut self.instance_infos,
+            &self.scene_graph,
+            &self.instancing,
+            &moved,
+        ));
+        for camera in &mut self.cameras {
+            camera.world_transform =
+                self.root_transform * self.scene_graph.node(camera.node_index).world_transform();
+        }
+        for light in &mut self.lights {
+            light.world_transform =
+                self.root_transform * self.scene_graph.node(light.node_index).world_transform();
+        }
+        self.light_buffer = create_light_buffer(device, &self.lights);
+        self.update_deformed_meshes(device);
+        self.mark_instances_dirty(InstanceUpdate::Transforms);
+        self.commit_instances(device);
+    }
+
+    /// Re-blends morph targets with the current node weights and re-skins
+    /// every skinned mesh from the current node transforms, and replaces the
+    /// joint matrix buffer. Meshes whose vertices moved get a new vertex
+    /// buffer and BLAS, the others keep theirs.
+    /// [`animate`](Self::animate) does this already.
+    pub fn update_deformed_meshes(&mut self, device: &maligog::Device) {
+        if self.deformed_meshes.is_empty() {
+            return;
+        }
+        let (joint_matrices, moved) = deform::update_deformed_meshes(
+            device,
+            &mut self.deformed_meshes,
+            &self.mesh_data,
+            &self.skins,
+            &self.scene_graph,
+        );
+        self.joint_matrix_buffer = create_joint_matrix_buffer(device, &joint_matrices);
+        for (deformed, moved) in self.deformed_meshes.iter().zip(moved) {
+            if !moved {
+                continue;
+            }
+            for (instance_index, info) in self.instance_infos.iter().enumerate() {
+                if info.node_index == Some(deformed.node_index)
+                    && info.mesh_index == deformed.mesh_index
+                {
+                    self.changed_instances.insert(instance_index);
+                }
+            }
+        }
+        self.mark_instances_dirty(InstanceUpdate::Transforms);
+    }
+
+    fn mark_instances_dirty(&mut self, update: InstanceUpdate) {
+        self.pending_instance_update = self.pending_instance_update.max(Some(update));
+    }
+
+    /// Moves an instance. Takes effect on the next [`commit_instances`](Self::commit_instances).
+    pub fn set_instance_transform(&mut self, instance_index: usize, transform: glam::Mat4) {
+        self.instance_infos[instance_index].transform = transform;
+        self.changed_instances.insert(instance_index);
+        self.mark_instances_dirty(InstanceUpdate::Transforms);
+    }
+
+    /// Hides or shows an instance without changing instance indices. Takes
+    /// effect on the next [`commit_instances`](Self::commit_instances).
+    pub fn set_instance_visible(&mut self, instance_index: usize, visible: bool) {
+        self.instance_infos[instance_index].visible = visible;
+        self.changed_instances.insert(instance_index);
+        self.mark_instances_dirty(InstanceUpdate::Transforms);
+    }
+
+    /// Adds an instance of mesh `mesh_index` and returns its index. The mask
+    /// and SBT offset are copied from an existing instance of the same mesh,
+    /// falling back to a visible-to-all mask and the mesh index.
+    pub fn add_instance(&mut self, mesh_index: usize, transform: glam::Mat4) -> usize {
+        let (mask, sbt_offset) = self
+            .instance_infos
+            .iter()
+            .find(|info| info.mesh_index == mesh_index)
+            .map_or((0xff, mesh_index as u32), |info| {
+                (info.mask, info.sbt_offset)
+            });
+        self.instance_infos.push(InstanceInfo {
+            node_index: None,
+            gpu_instance: None,
+            mesh_index,
+            custom_index: 0,
+            mask,
+            sbt_offset,
+            transform,
+            visible: true,
+            extras: None,
+        });
+        self.mark_instances_dirty(InstanceUpdate::Rebuild);
+        self.instance_infos.len() - 1
+    }
+
+    /// Removes an instance, shifting the indices of all instances after it.
+    pub fn remove_instance(&mut self, instance_index: usize) -> InstanceInfo {
+        self.mark_instances_dirty(InstanceUpdate::Rebuild);
+        self.instance_infos.remove(instance_index)
+    }
+
+    /// Applies pending instance edits: rebuilds the TLAS and replaces the
+    /// transform, emissive triangle and instance bounds buffers, along with
+    /// the geometry and instance attribute buffers when instances were added
+    /// or removed. Only the emissive triangles and bounds of changed
+    /// instances are recomputed unless instances were added or removed.
+    /// Descriptor sets created from this scene have to be recreated after
+    /// every commit.
+    ///
+    /// Panics when the instances have more geometries than 24-bit custom
+    /// indices can address.
+    pub fn commit_instances(&mut self, device: &maligog::Device) {
+        let update = match self.pending_instance_update.take() {
+            Some(update) => update,
+            None => return,
+        };
+        if update == InstanceUpdate::Rebuild {
+            let mut custom_index = 0;
+            for info in &mut self.instance_infos {
+                info.custom_index = custom_index;
+                custom_index += self.mesh_data.mesh_infos[info.mesh_index]
+                    .primitive_infos
+                    .len() as u32;
+            }
+            assert!(
+                gpu::custom_indices_fit(&self.instance_infos),
+                "instance custom indices exceed {}",
+                MAX_CUSTOM_INDEX
+            );
+            self.scene_graph.link_instances(&self.instance_infos);
+        }
+
+        create_flipped_blases(
+            device,
+            &self.mesh_data,
+            &mut self.flipped_blases,
+            &mut self.deformed_meshes,
+            &self.instance_infos,
+            &self.root_transform,
+        );
+        let blas_instances = create_blas_instances(
+            device,
+            &self.blases,
+            &self.flipped_blases,
+            &self.deformed_meshes,
+            &self.instance_infos,
+            &self.root_transform,
+        );
+        let instance_geometry = maligog::InstanceGeometry::new(device, &blas_instances);
+        // maligog can neither refit a TLAS nor write into an existing buffer
+        self.tlas = device.create_top_level_acceleration_structure(
+            self.doc.default_scene().unwrap().name(),
+            &[instance_geometry],
+        );
+        self.instance_data.transform_buffer = create_transform_buffer(device, &blas_instances);
+        if update == InstanceUpdate::Rebuild {
+            self.instance_data.geometry_buffer =
+                create_geometry_buffer(device, &self.instance_infos, &self.mesh_data.mesh_infos);
+            self.instance_data.attribute_buffer = create_instance_attribute_buffer(
+                device,
+                &self.instance_infos,
+                &self.instancing,
+                &self.instance_attribute_names,
+            );
+        }
+
+        let changed_instances = std::mem::take(&mut self.changed_instances);
+        if update == InstanceUpdate::Rebuild {
+            self.emissive_triangles = emissive::gather_emissive_triangles(
+                &self.instance_infos,
+                &self.mesh_data.mesh_infos,
+                &self.mesh_data.geometry,
+                &self.material_radiance,
+                &self.root_transform,
+            );
+            self.instance_bounds = bounds::gather_instance_bounds(
+                &self.instance_infos,
+                &self.mesh_data.mesh_infos,
+                &self.deformed_meshes,
+                &self.root_transform,
+            );
+        } else {
+            self.emissive_triangles
+                .retain(|triangle| !changed_instances.contains(&triangle.instance_index));
+            for &instance_index in &changed_instances {
+                emissive::gather_instance_emissive_triangles(
+                    &mut self.emissive_triangles,
+                    instance_index,
+                    &self.instance_infos,
+                    &self.mesh_data.mesh_infos,
+                    &self.mesh_data.geometry,
+                    &self.material_radiance,
+                    &self.root_transform,
+                );
+                self.instance_bounds[instance_index] = bounds::instance_bounds(
+                    &self.instance_infos[instance_index],
+                    &self.mesh_data.mesh_infos,
+                    &self.deformed_meshes,
+                    &self.root_transform,
+                );
+            }
+            // keep the order a full gather produces
+            self.emissive_triangles
+                .sort_by_key(|triangle| triangle.instance_index);
+        }
+        self.emissive_triangle_buffer =
+            create_emissive_triangle_buffer(device, &self.emissive_triangles);
+        self.instance_data.bounds_buffer =
+            create_instance_bounds_buffer(device, &self.instance_bounds);
+    }
+
+    pub fn material_buffer(&self) -> maligog::BufferView {
+        maligog::BufferView {
+            buffer: self.material_buffer.clone(),
+            offset: 0,
+        }
+    }
+
+    pub fn primitive_buffer(&self) -> maligog::BufferView {
+        maligog::BufferView {
+            buffer: self.mesh_data.primitive_buffer.clone(),
+            offset: 0,
+        }
+    }
+
+    /// The TLAS, geometry and material buffers, samplers and images, by
+    /// their binding in [`bindings`].
+    pub fn descriptors(&self) -> SceneDescriptors {
+        descriptor::gather_scene_descriptors(self, &self.empty_buffer)
+    }
+}
+
+/// Places the instances of every node flagged in `moved` at the node's world transform
+/// and returns their indices.
+fn follow_nodes(
+    instance_infos: &mut [InstanceInfo],
+    scene_graph: &SceneGraph,
+    instancing: &[Option<NodeInstancing>],
+    moved: &[bool],
+) -> Vec<usize> {
+    let mut followed = Vec::new();
+    for (instance_index, info) in instance_infos.iter_mut().enumerate() {
+        if let Some(node_index) = info.node_index.filter(|&n| moved[n]) {
+            info.transform = scene_graph.node(node_index).world_transform()
+                * instancing::instance_transform(instancing, info);
+            followed.push(instance_index);
+        }
+    }
+    followed
 }
 
 #[test]
@@ -583,3 +2021,71 @@ fn test_general() {
         let scene = Scene::from_file(Some("test scene"), &device, gltf_path);
     }
 }
+
+#[test]
+fn test_animate_keeps_instance_edits() {
+    let node = |children: Vec<usize>| SceneNode {
+        name: None,
+        parent: None,
+        children,
+        transform: NodeTransform::from_gltf(gltf::scene::Transform::Matrix {
+            matrix: glam::Mat4::IDENTITY.to_cols_array_2d(),
+        }),
+        world_transform: glam::Mat4::IDENTITY,
+        mesh: Some(0),
+        skin: None,
+        camera: None,
+        light: None,
+        extras: None,
+        weights: Vec::new(),
+        instances: Vec::new(),
+    };
+    let mut scene_graph = SceneGraph {
+        nodes: vec![node(vec![1]), node(Vec::new()), node(Vec::new())],
+        roots: vec![0, 2],
+    };
+    scene_graph.nodes[1].parent = Some(0);
+    let mut instance_infos = (0..3)
+        .map(|node_index| InstanceInfo {
+            node_index: Some(node_index),
+            gpu_instance: None,
+            mesh_index: 0,
+            custom_index: 0,
+            mask: 0xff,
+            sbt_offset: 0,
+            transform: glam::Mat4::IDENTITY,
+            visible: true,
+            extras: None,
+        })
+        .collect::<Vec<_>>();
+    let edit = glam::Mat4::from_translation(glam::Vec3::Z);
+    instance_infos[2].transform = edit;
+
+    let animation = Animation {
+        name: None,
+        channels: vec![AnimationChannel {
+            node_index: 0,
+            property: Property::Translation,
+            interpolation: Interpolation::Step,
+            inputs: vec![0.0],
+            outputs: vec![1.0, 0.0, 0.0],
+            components: 3,
+        }],
+        duration: 0.0,
+    };
+    animation.apply(0.0, &mut scene_graph);
+    scene_graph.update_world_transforms();
+    let moved = scene_graph.subtrees(animation.transformed_nodes());
+    let followed = follow_nodes(
+        &mut instance_infos,
+        &scene_graph,
+        &[None, None, None],
+        &moved,
+    );
+
+    let moved_to = glam::Mat4::from_translation(glam::Vec3::X);
+    assert_eq!(instance_infos[0].transform, moved_to);
+    assert_eq!(instance_infos[1].transform, moved_to);
+    assert_eq!(instance_infos[2].transform, edit);
+    assert_eq!(followed, [0, 1]);
+}
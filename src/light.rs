@@ -0,0 +1,127 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::scene_graph::SceneGraph;
+
+pub const LIGHT_TYPE_DIRECTIONAL: u32 = 0;
+pub const LIGHT_TYPE_POINT: u32 = 1;
+pub const LIGHT_TYPE_SPOT: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    /// Cone angles in radians, measured from the spot direction.
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+/// A `KHR_lights_punctual` light placed in the scene by a node.
+#[derive(Clone, Debug)]
+pub struct Light {
+    pub name: Option<String>,
+    pub light_index: usize,
+    pub node_index: usize,
+    pub kind: LightKind,
+    /// Linear RGB.
+    pub color: glam::Vec3,
+    /// Candela for point and spot lights, lux for directional lights.
+    pub intensity: f32,
+    /// Distance at which the light is cut off, `None` for no cut-off.
+    pub range: Option<f32>,
+    /// World transform of the node carrying the light. Directional and spot
+    /// lights shine down the node's local -Z axis.
+    pub world_transform: glam::Mat4,
+}
+
+impl Light {
+    pub fn position(&self) -> glam::Vec3 {
+        self.world_transform.transform_point3(glam::Vec3::ZERO)
+    }
+
+    pub fn direction(&self) -> glam::Vec3 {
+        self.world_transform
+            .transform_vector3(-glam::Vec3::Z)
+            .normalize()
+    }
+
+    pub fn gpu_light(&self) -> GpuLight {
+        let (ty, cos_inner_cone_angle, cos_outer_cone_angle) = match self.kind {
+            LightKind::Directional => (LIGHT_TYPE_DIRECTIONAL, -1.0, -1.0),
+            LightKind::Point => (LIGHT_TYPE_POINT, -1.0, -1.0),
+            LightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => (
+                LIGHT_TYPE_SPOT,
+                inner_cone_angle.cos(),
+                outer_cone_angle.cos(),
+            ),
+        };
+        GpuLight {
+            position: self.position().to_array(),
+            ty,
+            direction: self.direction().to_array(),
+            range: self.range.unwrap_or(f32::INFINITY),
+            color: self.color.to_array(),
+            intensity: self.intensity,
+            cos_inner_cone_angle,
+            cos_outer_cone_angle,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// std430 light record, see [`bindings::LIGHT_BUFFER`](crate::bindings::LIGHT_BUFFER).
+///
+/// `ty` is one of the `LIGHT_TYPE_*` constants. `range` is infinite when the
+/// light has no cut-off, and the cone cosines are only meaningful for spot lights.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct GpuLight {
+    pub position: [f32; 3],
+    pub ty: u32,
+    pub direction: [f32; 3],
+    pub range: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub cos_inner_cone_angle: f32,
+    pub cos_outer_cone_angle: f32,
+    pub _padding: [u32; 2],
+}
+
+pub(crate) fn load_lights(doc: &gltf::Document, scene_graph: &SceneGraph) -> Vec<Light> {
+    scene_graph
+        .walk()
+        .into_iter()
+        .filter_map(|node_index| {
+            let light = doc.nodes().nth(node_index)?.light()?;
+            let kind = match light.kind() {
+                gltf::khr_lights_punctual::Kind::Directional => LightKind::Directional,
+                gltf::khr_lights_punctual::Kind::Point => LightKind::Point,
+                gltf::khr_lights_punctual::Kind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                } => LightKind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                },
+            };
+            Some(Light {
+                name: light.name().map(|s| s.to_owned()),
+                light_index: light.index(),
+                node_index,
+                kind,
+                color: glam::Vec3::from(light.color()),
+                intensity: light.intensity(),
+                range: light.range(),
+                world_transform: scene_graph.node(node_index).world_transform(),
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn gather_gpu_lights(lights: &[Light]) -> Vec<GpuLight> {
+    lights.iter().map(Light::gpu_light).collect()
+}
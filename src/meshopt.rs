@@ -0,0 +1,569 @@
+//! Decoder for `EXT_meshopt_compression` buffer views, following the
+//! bitstream of meshoptimizer's vertex and index codecs.
+
+use crate::raw_json::RawJson;
+
+pub(crate) const EXTENSION_NAME: &str = "EXT_meshopt_compression";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshoptError {
+    /// The header names a codec version this decoder does not know.
+    UnsupportedVersion(u8),
+    /// The stream ended early or has bytes left over.
+    Malformed,
+    UnknownMode(String),
+    UnknownFilter(String),
+    /// The byte stride is not allowed for the mode or filter.
+    InvalidStride(usize),
+}
+
+impl std::fmt::Display for MeshoptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshoptError::UnsupportedVersion(header) => {
+                write!(f, "unsupported meshopt header {:#x}", header)
+            }
+            MeshoptError::Malformed => write!(f, "malformed meshopt stream"),
+            MeshoptError::UnknownMode(mode) => write!(f, "unknown meshopt mode {}", mode),
+            MeshoptError::UnknownFilter(filter) => write!(f, "unknown meshopt filter {}", filter),
+            MeshoptError::InvalidStride(stride) => write!(f, "invalid meshopt stride {}", stride),
+        }
+    }
+}
+
+impl std::error::Error for MeshoptError {}
+
+const VERTEX_HEADER: u8 = 0xa0;
+const INDEX_HEADER: u8 = 0xe0;
+const SEQUENCE_HEADER: u8 = 0xd0;
+
+const BYTE_GROUP_SIZE: usize = 16;
+const BYTE_GROUP_DECODE_LIMIT: usize = 24;
+const VERTEX_BLOCK_SIZE_BYTES: usize = 8192;
+const VERTEX_BLOCK_MAX_SIZE: usize = 256;
+const TAIL_MAX_SIZE: usize = 32;
+
+/// Bounds checked reader over an encoded stream.
+struct Stream<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Stream<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    fn byte(&mut self) -> Result<u8, MeshoptError> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or(MeshoptError::Malformed)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], MeshoptError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or(MeshoptError::Malformed)?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn vbyte(&mut self) -> Result<u32, MeshoptError> {
+        let lead = self.byte()?;
+        if lead < 128 {
+            return Ok(lead as u32);
+        }
+        let mut result = (lead & 127) as u32;
+        let mut shift = 7;
+        for _ in 0..4 {
+            let group = self.byte()?;
+            result |= ((group & 127) as u32) << shift;
+            shift += 7;
+            if group < 128 {
+                break;
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn unzigzag8(v: u8) -> u8 {
+    (0u8.wrapping_sub(v & 1)) ^ (v >> 1)
+}
+
+fn unzigzag32(v: u32) -> u32 {
+    (v >> 1) ^ 0u32.wrapping_sub(v & 1)
+}
+
+fn decode_bytes_group(
+    stream: &mut Stream,
+    output: &mut [u8],
+    bits_log2: u8,
+) -> Result<(), MeshoptError> {
+    let bits = match bits_log2 {
+        0 => {
+            output.fill(0);
+            return Ok(());
+        }
+        3 => {
+            output.copy_from_slice(stream.bytes(BYTE_GROUP_SIZE)?);
+            return Ok(());
+        }
+        _ => 1 << bits_log2,
+    };
+    // packed values come first, escaped values follow in order
+    let packed = stream.bytes(BYTE_GROUP_SIZE * bits / 8)?;
+    let escape = (1u8 << bits) - 1;
+    for (i, value) in output.iter_mut().enumerate() {
+        let bit_offset = i * bits;
+        let shift = 8 - bits - bit_offset % 8;
+        let encoded = (packed[bit_offset / 8] >> shift) & escape;
+        *value = match encoded == escape {
+            true => stream.byte()?,
+            false => encoded,
+        };
+    }
+    Ok(())
+}
+
+fn decode_bytes(stream: &mut Stream, output: &mut [u8]) -> Result<(), MeshoptError> {
+    let group_count = output.len() / BYTE_GROUP_SIZE;
+    let header = stream.bytes(group_count.div_ceil(4))?;
+    for (group, chunk) in output.chunks_exact_mut(BYTE_GROUP_SIZE).enumerate() {
+        if stream.remaining() < BYTE_GROUP_DECODE_LIMIT {
+            return Err(MeshoptError::Malformed);
+        }
+        let bits_log2 = (header[group / 4] >> ((group % 4) * 2)) & 3;
+        decode_bytes_group(stream, chunk, bits_log2)?;
+    }
+    Ok(())
+}
+
+fn vertex_block_size(vertex_size: usize) -> usize {
+    let size = (VERTEX_BLOCK_SIZE_BYTES / vertex_size) & !(BYTE_GROUP_SIZE - 1);
+    size.min(VERTEX_BLOCK_MAX_SIZE)
+}
+
+/// Decodes the `ATTRIBUTES` mode, `count` vertices of `vertex_size` bytes.
+pub fn decode_vertex_buffer(
+    count: usize,
+    vertex_size: usize,
+    data: &[u8],
+) -> Result<Vec<u8>, MeshoptError> {
+    if vertex_size == 0 || vertex_size > 256 || !vertex_size.is_multiple_of(4) {
+        return Err(MeshoptError::InvalidStride(vertex_size));
+    }
+    if data.len() < 1 + vertex_size {
+        return Err(MeshoptError::Malformed);
+    }
+    let header = data[0];
+    if header & 0xf0 != VERTEX_HEADER || header & 0x0f > 0 {
+        return Err(MeshoptError::UnsupportedVersion(header));
+    }
+    let mut last_vertex = data[data.len() - vertex_size..].to_vec();
+    let mut stream = Stream { data, position: 1 };
+    let mut output = vec![0u8; count * vertex_size];
+    let block_size = vertex_block_size(vertex_size);
+    let mut deltas = [0u8; VERTEX_BLOCK_MAX_SIZE];
+    for block in output.chunks_mut(block_size * vertex_size) {
+        let block_count = block.len() / vertex_size;
+        let aligned_count = (block_count + BYTE_GROUP_SIZE - 1) & !(BYTE_GROUP_SIZE - 1);
+        for k in 0..vertex_size {
+            decode_bytes(&mut stream, &mut deltas[..aligned_count])?;
+            let mut previous = last_vertex[k];
+            for (i, delta) in deltas[..block_count].iter().enumerate() {
+                previous = unzigzag8(*delta).wrapping_add(previous);
+                block[i * vertex_size + k] = previous;
+            }
+            last_vertex[k] = previous;
+        }
+    }
+    if stream.remaining() != vertex_size.max(TAIL_MAX_SIZE) {
+        return Err(MeshoptError::Malformed);
+    }
+    Ok(output)
+}
+
+fn push_edge(fifo: &mut [[u32; 2]; 16], offset: &mut usize, a: u32, b: u32) {
+    fifo[*offset] = [a, b];
+    *offset = (*offset + 1) & 15;
+}
+
+fn push_vertex(fifo: &mut [u32; 16], offset: &mut usize, v: u32, advance: bool) {
+    fifo[*offset] = v;
+    *offset = (*offset + advance as usize) & 15;
+}
+
+/// Decodes the `TRIANGLES` mode into `count` indices.
+pub fn decode_index_buffer(count: usize, data: &[u8]) -> Result<Vec<u32>, MeshoptError> {
+    if !count.is_multiple_of(3) || data.len() < 1 + count / 3 + 16 {
+        return Err(MeshoptError::Malformed);
+    }
+    let header = data[0];
+    let version = header & 0x0f;
+    if header & 0xf0 != INDEX_HEADER || version > 1 {
+        return Err(MeshoptError::UnsupportedVersion(header));
+    }
+    let fec_max = if version >= 1 { 13 } else { 15 };
+
+    let mut edge_fifo = [[u32::MAX; 2]; 16];
+    let mut vertex_fifo = [u32::MAX; 16];
+    let mut edge_fifo_offset = 0usize;
+    let mut vertex_fifo_offset = 0usize;
+    let codes = &data[1..1 + count / 3];
+    let safe_end = data.len() - 16;
+    let code_aux_table = &data[safe_end..];
+    let mut stream = Stream {
+        data: &data[..safe_end],
+        position: 1 + count / 3,
+    };
+    let mut next = 0u32;
+    let mut last = 0u32;
+    let mut indices = Vec::with_capacity(count);
+    let decode_index = |stream: &mut Stream, last: u32| -> Result<u32, MeshoptError> {
+        Ok(last.wrapping_add(unzigzag32(stream.vbyte()?)))
+    };
+
+    for &code in codes {
+        let edge_fifo_at = |fifo: &[[u32; 2]; 16], offset: usize, fe: usize| {
+            fifo[offset.wrapping_sub(1 + fe) & 15]
+        };
+        let vertex_fifo_at =
+            |fifo: &[u32; 16], offset: usize, fe: usize| fifo[offset.wrapping_sub(fe) & 15];
+        if code < 0xf0 {
+            let fe = (code >> 4) as usize;
+            let [a, b] = edge_fifo_at(&edge_fifo, edge_fifo_offset, fe);
+            let fec = (code & 15) as usize;
+            let c = if fec < fec_max {
+                let c = match fec {
+                    0 => {
+                        next += 1;
+                        next - 1
+                    }
+                    _ => vertex_fifo_at(&vertex_fifo, vertex_fifo_offset, fec + 1),
+                };
+                push_vertex(&mut vertex_fifo, &mut vertex_fifo_offset, c, fec == 0);
+                c
+            } else {
+                last = match fec {
+                    // 13 and 14 are a delta of -1 and +1 from the last free index
+                    15 => decode_index(&mut stream, last)?,
+                    _ => last.wrapping_add((fec as u32).wrapping_sub(fec as u32 ^ 3)),
+                };
+                push_vertex(&mut vertex_fifo, &mut vertex_fifo_offset, last, true);
+                last
+            };
+            indices.extend_from_slice(&[a, b, c]);
+            push_edge(&mut edge_fifo, &mut edge_fifo_offset, c, b);
+            push_edge(&mut edge_fifo, &mut edge_fifo_offset, a, c);
+        } else {
+            let (code_aux, fea) = match code < 0xfe {
+                true => (code_aux_table[(code & 15) as usize], 0),
+                false => (stream.byte()?, if code == 0xfe { 0 } else { 15 }),
+            };
+            if code >= 0xfe && code_aux == 0 {
+                next = 0;
+            }
+            let feb = (code_aux >> 4) as usize;
+            let fec = (code_aux & 15) as usize;
+            // next is incremented for all three vertices before free indices
+            // are decoded, matching the encoder
+            let mut take = |fe: usize| match fe {
+                0 => {
+                    next += 1;
+                    next - 1
+                }
+                15 => 0,
+                _ => vertex_fifo_at(&vertex_fifo, vertex_fifo_offset, fe),
+            };
+            let mut a = take(fea);
+            let mut b = take(feb);
+            let mut c = take(fec);
+            if fea == 15 {
+                last = decode_index(&mut stream, last)?;
+                a = last;
+            }
+            if feb == 15 {
+                last = decode_index(&mut stream, last)?;
+                b = last;
+            }
+            if fec == 15 {
+                last = decode_index(&mut stream, last)?;
+                c = last;
+            }
+            indices.extend_from_slice(&[a, b, c]);
+            push_vertex(&mut vertex_fifo, &mut vertex_fifo_offset, a, true);
+            push_vertex(
+                &mut vertex_fifo,
+                &mut vertex_fifo_offset,
+                b,
+                feb == 0 || feb == 15,
+            );
+            push_vertex(
+                &mut vertex_fifo,
+                &mut vertex_fifo_offset,
+                c,
+                fec == 0 || fec == 15,
+            );
+            push_edge(&mut edge_fifo, &mut edge_fifo_offset, b, a);
+            push_edge(&mut edge_fifo, &mut edge_fifo_offset, c, b);
+            push_edge(&mut edge_fifo, &mut edge_fifo_offset, a, c);
+        }
+    }
+    if stream.remaining() != 0 {
+        return Err(MeshoptError::Malformed);
+    }
+    Ok(indices)
+}
+
+/// Decodes the `INDICES` mode into `count` indices.
+pub fn decode_index_sequence(count: usize, data: &[u8]) -> Result<Vec<u32>, MeshoptError> {
+    if data.len() < 1 + count + 4 {
+        return Err(MeshoptError::Malformed);
+    }
+    let header = data[0];
+    if header & 0xf0 != SEQUENCE_HEADER || header & 0x0f > 1 {
+        return Err(MeshoptError::UnsupportedVersion(header));
+    }
+    let mut stream = Stream {
+        data: &data[..data.len() - 4],
+        position: 1,
+    };
+    let mut last = [0u32; 2];
+    let mut indices = Vec::with_capacity(count);
+    for _ in 0..count {
+        let v = stream.vbyte()?;
+        // the low bit picks one of two baselines to delta against
+        let baseline = (v & 1) as usize;
+        let index = last[baseline].wrapping_add(unzigzag32(v >> 1));
+        last[baseline] = index;
+        indices.push(index);
+    }
+    if stream.remaining() != 0 {
+        return Err(MeshoptError::Malformed);
+    }
+    Ok(indices)
+}
+
+fn round_to_int(v: f32) -> i32 {
+    (v + if v >= 0.0 { 0.5 } else { -0.5 }) as i32
+}
+
+fn filter_octahedral(data: &mut [u8], stride: usize) -> Result<(), MeshoptError> {
+    let component_size = match stride {
+        4 => 1,
+        8 => 2,
+        _ => return Err(MeshoptError::InvalidStride(stride)),
+    };
+    let max = ((1 << (component_size * 8 - 1)) - 1) as f32;
+    let read = |bytes: &[u8], i: usize| match component_size {
+        1 => bytes[i] as i8 as f32,
+        _ => i16::from_le_bytes([bytes[2 * i], bytes[2 * i + 1]]) as f32,
+    };
+    for element in data.chunks_exact_mut(stride) {
+        let mut x = read(element, 0);
+        let mut y = read(element, 1);
+        let z = read(element, 2) - x.abs() - y.abs();
+        // fold the lower hemisphere back
+        let t = z.min(0.0);
+        x += if x >= 0.0 { t } else { -t };
+        y += if y >= 0.0 { t } else { -t };
+        let s = max / (x * x + y * y + z * z).sqrt();
+        for (i, v) in [x, y, z].iter().enumerate() {
+            let v = round_to_int(v * s);
+            match component_size {
+                1 => element[i] = v as i8 as u8,
+                _ => element[2 * i..2 * i + 2].copy_from_slice(&(v as i16).to_le_bytes()),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn filter_quaternion(data: &mut [u8], stride: usize) -> Result<(), MeshoptError> {
+    if stride != 8 {
+        return Err(MeshoptError::InvalidStride(stride));
+    }
+    let scale = 1.0 / 2f32.sqrt();
+    for element in data.chunks_exact_mut(8) {
+        let q = [0, 1, 2, 3].map(|i| i16::from_le_bytes([element[2 * i], element[2 * i + 1]]));
+        // the scale is stored in the high bits of the last component and the
+        // index of the largest, omitted component in its two low bits
+        let ss = scale / (q[3] | 3) as f32;
+        let x = q[0] as f32 * ss;
+        let y = q[1] as f32 * ss;
+        let z = q[2] as f32 * ss;
+        let w = (1.0 - x * x - y * y - z * z).max(0.0).sqrt();
+        let qc = (q[3] & 3) as usize;
+        let mut output = [0i16; 4];
+        output[(qc + 1) & 3] = round_to_int(x * 32767.0) as i16;
+        output[(qc + 2) & 3] = round_to_int(y * 32767.0) as i16;
+        output[(qc + 3) & 3] = round_to_int(z * 32767.0) as i16;
+        output[qc] = (w * 32767.0 + 0.5) as i16;
+        for (i, v) in output.iter().enumerate() {
+            element[2 * i..2 * i + 2].copy_from_slice(&v.to_le_bytes());
+        }
+    }
+    Ok(())
+}
+
+fn filter_exponential(data: &mut [u8], stride: usize) -> Result<(), MeshoptError> {
+    if !stride.is_multiple_of(4) {
+        return Err(MeshoptError::InvalidStride(stride));
+    }
+    for value in data.chunks_exact_mut(4) {
+        let v = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+        // 24 bit signed mantissa, 8 bit signed exponent
+        let mantissa = ((v << 8) as i32) >> 8;
+        let exponent = (v as i32) >> 24;
+        let decoded = mantissa as f32 * 2f32.powi(exponent);
+        value.copy_from_slice(&decoded.to_le_bytes());
+    }
+    Ok(())
+}
+
+/// Decodes one compressed buffer view into `count * stride` bytes.
+pub fn decode_buffer_view(
+    mode: &str,
+    filter: &str,
+    count: usize,
+    stride: usize,
+    data: &[u8],
+) -> Result<Vec<u8>, MeshoptError> {
+    let mut decoded = match mode {
+        "ATTRIBUTES" => decode_vertex_buffer(count, stride, data)?,
+        "TRIANGLES" | "INDICES" => {
+            let indices = match mode {
+                "TRIANGLES" => decode_index_buffer(count, data)?,
+                _ => decode_index_sequence(count, data)?,
+            };
+            match stride {
+                2 => indices
+                    .iter()
+                    .flat_map(|i| (*i as u16).to_le_bytes())
+                    .collect(),
+                4 => bytemuck::cast_slice(&indices).to_vec(),
+                _ => return Err(MeshoptError::InvalidStride(stride)),
+            }
+        }
+        _ => return Err(MeshoptError::UnknownMode(mode.to_owned())),
+    };
+    match filter {
+        "NONE" => {}
+        "OCTAHEDRAL" => filter_octahedral(&mut decoded, stride)?,
+        "QUATERNION" => filter_quaternion(&mut decoded, stride)?,
+        "EXPONENTIAL" => filter_exponential(&mut decoded, stride)?,
+        _ => return Err(MeshoptError::UnknownFilter(filter.to_owned())),
+    }
+    Ok(decoded)
+}
+
+/// Whether `buffer_index` only exists as a fallback for compressed views and
+/// has no data of its own.
+pub(crate) fn is_fallback_buffer(raw_json: &RawJson, buffer_index: usize) -> bool {
+    raw_json
+        .buffer_extension(buffer_index, EXTENSION_NAME)
+        .and_then(|e| e.get("fallback")?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Decodes every compressed buffer view into the range of its own buffer, so
+/// that accessors read the decoded data.
+pub(crate) fn decode_buffer_views(
+    doc: &gltf::Document,
+    raw_json: &RawJson,
+    buffers: &mut [gltf::buffer::Data],
+) -> Result<(), MeshoptError> {
+    for view in doc.views() {
+        let extension = match raw_json.view_extension(view.index(), EXTENSION_NAME) {
+            Some(extension) => extension,
+            None => continue,
+        };
+        let field = |name: &str| {
+            extension
+                .get(name)
+                .and_then(|v| v.as_u64())
+                .map(|v| v as usize)
+        };
+        let (buffer, length, stride, count) = match (
+            field("buffer"),
+            field("byteLength"),
+            field("byteStride"),
+            field("count"),
+        ) {
+            (Some(buffer), Some(length), Some(stride), Some(count)) => {
+                (buffer, length, stride, count)
+            }
+            _ => return Err(MeshoptError::Malformed),
+        };
+        let offset = field("byteOffset").unwrap_or(0);
+        let mode = extension
+            .get("mode")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        let filter = extension
+            .get("filter")
+            .and_then(|v| v.as_str())
+            .unwrap_or("NONE");
+        let encoded = buffers
+            .get(buffer)
+            .and_then(|b| b.get(offset..offset + length))
+            .ok_or(MeshoptError::Malformed)?;
+        let decoded = decode_buffer_view(mode, filter, count, stride, encoded)?;
+        buffers[view.buffer().index()]
+            .0
+            .get_mut(view.offset()..view.offset() + decoded.len())
+            .ok_or(MeshoptError::Malformed)?
+            .copy_from_slice(&decoded);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_decode_index_streams() {
+    // one table coded triangle of new vertices, then one reusing its second edge
+    let mut triangles = vec![0xe1, 0xf0, 0x10];
+    triangles.extend_from_slice(&[0; 16]);
+    assert_eq!(
+        decode_index_buffer(6, &triangles),
+        Ok(vec![0, 1, 2, 2, 1, 3])
+    );
+    assert_eq!(
+        decode_index_sequence(3, &[0xd1, 20, 4, 6, 0, 0, 0, 0]),
+        Ok(vec![5, 6, 4])
+    );
+}
+
+#[test]
+fn test_decode_vertex_buffer() {
+    let mut data = vec![VERTEX_HEADER];
+    // first byte of each vertex: raw zigzag deltas +1, -1
+    data.push(0b11);
+    data.extend_from_slice(&[2, 1]);
+    data.extend_from_slice(&[0; 14]);
+    // other bytes repeat the base vertex
+    data.extend_from_slice(&[0; 3]);
+    data.extend_from_slice(&[0; 28]);
+    data.extend_from_slice(&[10, 20, 30, 40]);
+    assert_eq!(
+        decode_vertex_buffer(2, 4, &data),
+        Ok(vec![11, 20, 30, 40, 10, 20, 30, 40])
+    );
+}
+
+#[test]
+fn test_decode_octahedral_filter() {
+    // (0.6, 0, -0.8), whose lower hemisphere encoding folds x and y outwards
+    let mut data = vec![VERTEX_HEADER];
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&[0; 28]);
+    data.extend_from_slice(&[127, 73, 127, 0]);
+    assert_eq!(
+        decode_buffer_view("ATTRIBUTES", "OCTAHEDRAL", 1, 4, &data),
+        Ok(vec![76, 0, -102i8 as u8, 0])
+    );
+}
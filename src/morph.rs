@@ -0,0 +1,83 @@
+use crate::accessor;
+
+/// Displacements of one morph target of a primitive, one per vertex.
+#[derive(Clone, Debug, Default)]
+pub struct MorphTarget {
+    pub positions: Option<Vec<[f32; 3]>>,
+    pub normals: Option<Vec<[f32; 3]>>,
+    pub tangents: Option<Vec<[f32; 3]>>,
+}
+
+/// Adds `weights[i] * deltas[i]` to `base` for every target. Targets without
+/// displacements for the attribute, and weights beyond the target count, are skipped.
+pub fn blend_morph_targets(
+    base: &[[f32; 3]],
+    deltas: &[Option<&[[f32; 3]]>],
+    weights: &[f32],
+) -> Vec<[f32; 3]> {
+    let mut blended = base.to_vec();
+    for (deltas, weight) in deltas.iter().zip(weights) {
+        let deltas = match deltas {
+            Some(deltas) if *weight != 0.0 => deltas,
+            _ => continue,
+        };
+        for (vertex, delta) in blended.iter_mut().zip(deltas.iter()) {
+            for i in 0..3 {
+                vertex[i] += weight * delta[i];
+            }
+        }
+    }
+    blended
+}
+
+/// Blends the position displacements of `targets` onto `base`.
+pub fn blend_positions(
+    base: &[[f32; 3]],
+    targets: &[MorphTarget],
+    weights: &[f32],
+) -> Vec<[f32; 3]> {
+    let deltas = targets
+        .iter()
+        .map(|t| t.positions.as_deref())
+        .collect::<Vec<_>>();
+    blend_morph_targets(base, &deltas, weights)
+}
+
+/// Reads the morph targets of a primitive, accepting the quantized
+/// displacement types of `KHR_mesh_quantization`.
+pub(crate) fn read_morph_targets(
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+) -> Vec<MorphTarget> {
+    primitive
+        .morph_targets()
+        .map(|target| MorphTarget {
+            positions: target
+                .positions()
+                .map(|a| accessor::read_vectors(&a, buffers)),
+            normals: target
+                .normals()
+                .map(|a| accessor::read_vectors(&a, buffers)),
+            tangents: target
+                .tangents()
+                .map(|a| accessor::read_vectors(&a, buffers)),
+        })
+        .collect()
+}
+
+#[test]
+fn test_blend_morph_targets() {
+    let targets = vec![
+        MorphTarget {
+            positions: Some(vec![[1.0, 0.0, 0.0]]),
+            ..Default::default()
+        },
+        MorphTarget::default(),
+        MorphTarget {
+            positions: Some(vec![[0.0, 2.0, 0.0]]),
+            ..Default::default()
+        },
+    ];
+    let blended = blend_positions(&[[1.0, 1.0, 1.0]], &targets, &[0.5, 1.0, 0.25]);
+    assert_eq!(blended, vec![[1.5, 1.5, 1.0]]);
+}
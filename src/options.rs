@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use crate::axis::AxisConversion;
+use crate::draco::DracoDecoder;
+use crate::instance::InstancePolicy;
+use crate::repack::PackingSpec;
+
+/// Options controlling how a glTF document is turned into a [`Scene`](crate::Scene).
+#[derive(Clone, Default)]
+pub struct LoadOptions {
+    /// When set, every material gets a packed texture built from its existing
+    /// textures according to this spec, see [`MaterialInfo::packed_texture`](crate::MaterialInfo).
+    pub texture_packing: Option<PackingSpec>,
+    /// Chooses the mask and SBT offset of each instance, defaults to
+    /// [`MeshIndexPolicy`](crate::MeshIndexPolicy).
+    pub instance_policy: Option<Arc<dyn InstancePolicy>>,
+    /// Keep positions stored as normalized 16-bit integers quantized on the
+    /// GPU and build their BLASes from them, instead of converting to floats.
+    /// See [`PositionFormat`](crate::PositionFormat).
+    pub quantized_positions: bool,
+    /// The device reports `VK_FORMAT_FEATURE_ACCELERATION_STRUCTURE_VERTEX_BUFFER_BIT_KHR`
+    /// for `R16G16B16A16_UNORM`, which is not a mandatory vertex format.
+    /// maligog can't query format features, so callers check it themselves.
+    /// Without it, unsigned normalized positions are converted to floats even
+    /// with `quantized_positions`.
+    pub unorm16_vertex_format: bool,
+    /// Decodes `KHR_draco_mesh_compression` primitives, defaults to
+    /// [`SequentialDracoDecoder`](crate::SequentialDracoDecoder), which only
+    /// reads streams without entropy coding. Primitives it can't read load
+    /// from their uncompressed fallback accessors when they have any.
+    pub draco_decoder: Option<Arc<dyn DracoDecoder>>,
+    /// Load documents requiring extensions the loader ignores instead of
+    /// failing with [`LoadError::UnsupportedExtensions`](crate::LoadError).
+    /// See [`SupportReport`](crate::SupportReport).
+    pub best_effort: bool,
+    /// The coordinate system the asset was authored in when it does not
+    /// follow glTF's, applied on top of the scene's node transforms. See
+    /// [`Scene::root_transform`](crate::Scene::root_transform), and
+    /// [`Scene::tlas`](crate::Scene::tlas) for mirroring conversions.
+    pub axis_conversion: AxisConversion,
+    /// Recenter the scene on the center of its bounds and scale it uniformly
+    /// so that the longest side of the bounds has this length. See
+    /// [`Scene::normalization`](crate::Scene::normalization).
+    pub normalize_extent: Option<f32>,
+}
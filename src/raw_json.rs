@@ -0,0 +1,58 @@
+use gltf::json::Value;
+
+/// The document's JSON as written, for extensions `gltf` does not parse.
+#[derive(Clone, Debug)]
+pub(crate) struct RawJson {
+    root: Value,
+}
+
+impl RawJson {
+    /// Parses a `.gltf` file or the JSON chunk of a `.glb` file.
+    pub fn from_slice(data: &[u8]) -> Result<Self, gltf::Error> {
+        let root = match data.starts_with(b"glTF") {
+            true => {
+                let glb = gltf::binary::Glb::from_slice(data)?;
+                gltf::json::deserialize::from_slice(&glb.json)?
+            }
+            false => gltf::json::deserialize::from_slice(data)?,
+        };
+        Ok(Self { root })
+    }
+
+    pub fn object(&self, collection: &str, index: usize) -> Option<&Value> {
+        self.root.get(collection)?.get(index)
+    }
+
+    fn extension<'a>(object: &'a Value, name: &str) -> Option<&'a Value> {
+        object.get("extensions")?.get(name)
+    }
+
+    pub fn root_extension(&self, name: &str) -> Option<&Value> {
+        Self::extension(&self.root, name)
+    }
+
+    pub fn buffer_extension(&self, buffer_index: usize, name: &str) -> Option<&Value> {
+        Self::extension(self.object("buffers", buffer_index)?, name)
+    }
+
+    pub fn view_extension(&self, view_index: usize, name: &str) -> Option<&Value> {
+        Self::extension(self.object("bufferViews", view_index)?, name)
+    }
+
+    pub fn primitive_extension(
+        &self,
+        mesh_index: usize,
+        primitive_index: usize,
+        name: &str,
+    ) -> Option<&Value> {
+        let primitive = self
+            .object("meshes", mesh_index)?
+            .get("primitives")?
+            .get(primitive_index)?;
+        Self::extension(primitive, name)
+    }
+
+    pub fn node_extension(&self, node_index: usize, name: &str) -> Option<&Value> {
+        Self::extension(self.object("nodes", node_index)?, name)
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use crate::util;
+use crate::Texture;
+
+/// A texture slot of a glTF material that can be used as a repacking source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureSlot {
+    BaseColor,
+    MetallicRoughness,
+    Normal,
+    Occlusion,
+    Emissive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+    R,
+    G,
+    B,
+    A,
+}
+
+impl Channel {
+    fn offset(self) -> usize {
+        match self {
+            Channel::R => 0,
+            Channel::G => 1,
+            Channel::B => 2,
+            Channel::A => 3,
+        }
+    }
+}
+
+/// Where an output channel of a packed texture takes its value from.
+///
+/// A `Texture` source whose slot is empty on a material reads as 255, which
+/// matches the glTF rule that a missing texture leaves its factor unscaled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChannelSource {
+    Texture { slot: TextureSlot, channel: Channel },
+    Constant(u8),
+}
+
+/// Describes how to build a packed RGBA texture out of channels of a
+/// material's existing textures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PackingSpec {
+    pub r: ChannelSource,
+    pub g: ChannelSource,
+    pub b: ChannelSource,
+    pub a: ChannelSource,
+}
+
+impl PackingSpec {
+    /// `R = occlusion.r, G = mr.g, B = mr.b`, the layout most ORM shaders expect.
+    pub fn orm() -> Self {
+        Self {
+            r: ChannelSource::Texture {
+                slot: TextureSlot::Occlusion,
+                channel: Channel::R,
+            },
+            g: ChannelSource::Texture {
+                slot: TextureSlot::MetallicRoughness,
+                channel: Channel::G,
+            },
+            b: ChannelSource::Texture {
+                slot: TextureSlot::MetallicRoughness,
+                channel: Channel::B,
+            },
+            a: ChannelSource::Constant(255),
+        }
+    }
+
+    /// Parses a spec such as `"R = occlusion.r, G = mr.g, B = mr.b, A = 0"`.
+    ///
+    /// Slots are `base_color`, `mr` (or `metallic_roughness`), `normal`,
+    /// `occlusion` and `emissive`. Channels that are not mentioned are set to 255.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut channels = [ChannelSource::Constant(255); 4];
+        for assignment in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let mut parts = assignment.splitn(2, '=').map(str::trim);
+            let target = parse_channel(parts.next()?)?;
+            let source = parts.next()?;
+            channels[target.offset()] = match source.parse::<u8>() {
+                Ok(value) => ChannelSource::Constant(value),
+                Err(_) => {
+                    let mut source = source.splitn(2, '.');
+                    let slot = match source.next()?.to_ascii_lowercase().as_str() {
+                        "base_color" | "basecolor" => TextureSlot::BaseColor,
+                        "mr" | "metallic_roughness" => TextureSlot::MetallicRoughness,
+                        "normal" => TextureSlot::Normal,
+                        "occlusion" => TextureSlot::Occlusion,
+                        "emissive" => TextureSlot::Emissive,
+                        _ => return None,
+                    };
+                    let channel = parse_channel(source.next()?)?;
+                    ChannelSource::Texture { slot, channel }
+                }
+            };
+        }
+        Some(Self {
+            r: channels[0],
+            g: channels[1],
+            b: channels[2],
+            a: channels[3],
+        })
+    }
+
+    fn channels(&self) -> [ChannelSource; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+fn parse_channel(s: &str) -> Option<Channel> {
+    match s.to_ascii_lowercase().as_str() {
+        "r" => Some(Channel::R),
+        "g" => Some(Channel::G),
+        "b" => Some(Channel::B),
+        "a" => Some(Channel::A),
+        _ => None,
+    }
+}
+
+fn slot_texture<'a>(
+    material: &gltf::Material<'a>,
+    slot: TextureSlot,
+) -> Option<gltf::texture::Texture<'a>> {
+    match slot {
+        TextureSlot::BaseColor => material
+            .pbr_metallic_roughness()
+            .base_color_texture()
+            .map(|t| t.texture()),
+        TextureSlot::MetallicRoughness => material
+            .pbr_metallic_roughness()
+            .metallic_roughness_texture()
+            .map(|t| t.texture()),
+        TextureSlot::Normal => material.normal_texture().map(|t| t.texture()),
+        TextureSlot::Occlusion => material.occlusion_texture().map(|t| t.texture()),
+        TextureSlot::Emissive => material.emissive_texture().map(|t| t.texture()),
+    }
+}
+
+/// Packs `sources` (one optional image and channel per output channel) into a
+/// single RGBA image. Sources of differing size are resampled to the largest one.
+pub fn pack_channels(
+    sources: [Option<(&image::RgbaImage, Channel)>; 4],
+    constants: [u8; 4],
+) -> image::RgbaImage {
+    let (width, height) = sources.iter().flatten().fold((1, 1), |(w, h), (img, _)| {
+        (w.max(img.width()), h.max(img.height()))
+    });
+    let resized = sources
+        .iter()
+        .map(|source| {
+            source.map(|(img, channel)| {
+                let img = if img.dimensions() != (width, height) {
+                    std::borrow::Cow::Owned(image::imageops::resize(
+                        img,
+                        width,
+                        height,
+                        image::imageops::FilterType::Triangle,
+                    ))
+                } else {
+                    std::borrow::Cow::Borrowed(img)
+                };
+                (img, channel)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut packed = image::RgbaImage::new(width, height);
+    for (x, y, pixel) in packed.enumerate_pixels_mut() {
+        for (i, source) in resized.iter().enumerate() {
+            pixel.0[i] = match source {
+                Some((img, channel)) => img.get_pixel(x, y).0[channel.offset()],
+                None => constants[i],
+            };
+        }
+    }
+    packed
+}
+
+/// Packed images built for a document, plus the packed texture of every
+/// material (including the default material at index 0).
+pub(crate) struct PackedTextures {
+    pub images: Vec<image::RgbaImage>,
+    pub material_textures: Vec<Option<Texture>>,
+}
+
+/// Builds one packed image per distinct combination of source images used by
+/// the materials. Packed images are numbered from `image_index_base`.
+pub(crate) fn repack_textures(
+    spec: &PackingSpec,
+    gltf_materials: gltf::iter::Materials,
+    gltf_images: &[gltf::image::Data],
+    image_index_base: u32,
+) -> PackedTextures {
+    let mut decoded: HashMap<usize, image::RgbaImage> = HashMap::new();
+    let mut packed_indices: HashMap<[Option<usize>; 4], u32> = HashMap::new();
+    let mut images = Vec::new();
+    let mut material_textures = vec![None];
+
+    for material in gltf_materials {
+        let channels = spec.channels();
+        let textures = channels
+            .iter()
+            .map(|source| match source {
+                ChannelSource::Texture { slot, .. } => slot_texture(&material, *slot),
+                ChannelSource::Constant(_) => None,
+            })
+            .collect::<Vec<_>>();
+        if textures.iter().all(Option::is_none) {
+            material_textures.push(None);
+            continue;
+        }
+
+        let mut key = [None; 4];
+        for (k, texture) in key.iter_mut().zip(&textures) {
+            *k = texture.as_ref().map(|t| t.source().index());
+        }
+        let sampler_index = textures
+            .iter()
+            .flatten()
+            .next()
+            .and_then(|t| t.sampler().index())
+            .map_or(0, |i| i as u32 + 1);
+
+        let image_index = match packed_indices.get(&key) {
+            Some(index) => *index,
+            None => {
+                for image_index in key.iter().flatten() {
+                    decoded.entry(*image_index).or_insert_with(|| {
+                        util::convert_image_to_rgba8(&gltf_images[*image_index])
+                    });
+                }
+                let mut sources = [None; 4];
+                let mut constants = [255; 4];
+                for (i, source) in channels.iter().enumerate() {
+                    match (source, key[i]) {
+                        (ChannelSource::Texture { channel, .. }, Some(image_index)) => {
+                            sources[i] = Some((&decoded[&image_index], *channel))
+                        }
+                        (ChannelSource::Constant(value), _) => constants[i] = *value,
+                        _ => {}
+                    }
+                }
+                let index = image_index_base + images.len() as u32;
+                images.push(pack_channels(sources, constants));
+                packed_indices.insert(key, index);
+                index
+            }
+        };
+        material_textures.push(Some(Texture {
+            sampler_index,
+            image_index,
+        }));
+    }
+
+    PackedTextures {
+        images,
+        material_textures,
+    }
+}
+
+#[test]
+fn test_pack_channels() {
+    let occlusion = image::RgbaImage::from_pixel(2, 2, image::Rgba([10, 10, 10, 255]));
+    let mr = image::RgbaImage::from_pixel(4, 4, image::Rgba([0, 20, 30, 255]));
+    let spec = PackingSpec::parse("R = occlusion.r, G = mr.g, B = mr.b").unwrap();
+    assert_eq!(spec, PackingSpec::orm());
+
+    let packed = pack_channels(
+        [
+            Some((&occlusion, Channel::R)),
+            Some((&mr, Channel::G)),
+            Some((&mr, Channel::B)),
+            None,
+        ],
+        [255; 4],
+    );
+    assert_eq!(packed.dimensions(), (4, 4));
+    assert_eq!(packed.get_pixel(3, 3).0, [10, 20, 30, 255]);
+}
@@ -0,0 +1,58 @@
+use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+/// Sampler state derived from a glTF sampler, as passed to
+/// `device.create_sampler`.
+///
+/// maligog samplers only take the filters and U/V address modes, so mipmap
+/// mode, LOD clamp and anisotropy are left at the driver's defaults.
+#[derive(Clone, Copy)]
+pub struct SamplerInfo {
+    pub mag_filter: maligog::Filter,
+    pub min_filter: maligog::Filter,
+    pub address_mode_u: maligog::SamplerAddressMode,
+    pub address_mode_v: maligog::SamplerAddressMode,
+}
+
+fn convert_wrapping_mode(mode: WrappingMode) -> maligog::SamplerAddressMode {
+    match mode {
+        WrappingMode::ClampToEdge => maligog::SamplerAddressMode::CLAMP_TO_EDGE,
+        WrappingMode::MirroredRepeat => maligog::SamplerAddressMode::MIRRORED_REPEAT,
+        WrappingMode::Repeat => maligog::SamplerAddressMode::REPEAT,
+    }
+}
+
+impl SamplerInfo {
+    /// The glTF default sampler: repeat wrapping and implementation chosen
+    /// filtering, which we take to be linear.
+    pub(crate) fn gltf_default() -> Self {
+        Self {
+            mag_filter: maligog::Filter::LINEAR,
+            min_filter: maligog::Filter::LINEAR,
+            address_mode_u: maligog::SamplerAddressMode::REPEAT,
+            address_mode_v: maligog::SamplerAddressMode::REPEAT,
+        }
+    }
+
+    pub(crate) fn new(sampler: &gltf::texture::Sampler) -> Self {
+        let mag_filter = match sampler.mag_filter() {
+            Some(MagFilter::Nearest) => maligog::Filter::NEAREST,
+            Some(MagFilter::Linear) | None => maligog::Filter::LINEAR,
+        };
+        let min_filter = match sampler.min_filter() {
+            Some(MinFilter::Nearest)
+            | Some(MinFilter::NearestMipmapNearest)
+            | Some(MinFilter::NearestMipmapLinear) => maligog::Filter::NEAREST,
+            Some(MinFilter::Linear)
+            | Some(MinFilter::LinearMipmapNearest)
+            | Some(MinFilter::LinearMipmapLinear)
+            | None => maligog::Filter::LINEAR,
+        };
+
+        Self {
+            mag_filter,
+            min_filter,
+            address_mode_u: convert_wrapping_mode(sampler.wrap_s()),
+            address_mode_v: convert_wrapping_mode(sampler.wrap_t()),
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use crate::Extras;
+
+/// Local transform of a node, decomposed so animations can target each part.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeTransform {
+    pub translation: glam::Vec3,
+    pub rotation: glam::Quat,
+    pub scale: glam::Vec3,
+}
+
+impl NodeTransform {
+    pub fn from_gltf(transform: gltf::scene::Transform) -> Self {
+        let (translation, rotation, scale) = transform.decomposed();
+        Self {
+            translation: glam::Vec3::from(translation),
+            rotation: glam::Quat::from_array(rotation),
+            scale: glam::Vec3::from(scale),
+        }
+    }
+
+    pub fn matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+/// A node of the document, with its place in the hierarchy and what is attached to it.
+#[derive(Clone, Debug)]
+pub struct SceneNode {
+    pub(crate) name: Option<String>,
+    pub(crate) parent: Option<usize>,
+    pub(crate) children: Vec<usize>,
+    pub(crate) transform: NodeTransform,
+    pub(crate) world_transform: glam::Mat4,
+    pub(crate) mesh: Option<usize>,
+    pub(crate) skin: Option<usize>,
+    pub(crate) camera: Option<usize>,
+    pub(crate) light: Option<usize>,
+    /// Morph target weights, empty for nodes without a morphed mesh.
+    pub(crate) weights: Vec<f32>,
+    pub(crate) instances: Vec<usize>,
+    pub(crate) extras: Option<Arc<Extras>>,
+}
+
+impl SceneNode {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[usize] {
+        &self.children
+    }
+
+    pub fn local_transform(&self) -> &NodeTransform {
+        &self.transform
+    }
+
+    /// Transform from node space to world space. Nodes outside the loaded
+    /// glTF scene stay at identity.
+    pub fn world_transform(&self) -> glam::Mat4 {
+        self.world_transform
+    }
+
+    pub fn mesh(&self) -> Option<usize> {
+        self.mesh
+    }
+
+    pub fn skin(&self) -> Option<usize> {
+        self.skin
+    }
+
+    pub fn camera(&self) -> Option<usize> {
+        self.camera
+    }
+
+    /// Index of the `KHR_lights_punctual` light attached to this node.
+    pub fn light(&self) -> Option<usize> {
+        self.light
+    }
+
+    /// The node's `extras` and unknown extensions.
+    pub fn extras(&self) -> Option<&Extras> {
+        self.extras.as_deref()
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// Indices into [`Scene::instance_infos`](crate::Scene::instance_infos) of
+    /// the TLAS instances this node produced.
+    pub fn instances(&self) -> &[usize] {
+        &self.instances
+    }
+}
+
+/// Node hierarchy of the whole document, indexed by glTF node index.
+#[derive(Clone, Debug)]
+pub struct SceneGraph {
+    pub(crate) nodes: Vec<SceneNode>,
+    pub(crate) roots: Vec<usize>,
+}
+
+impl SceneGraph {
+    pub(crate) fn new(
+        doc: &gltf::Document,
+        scene: &gltf::Scene,
+        node_extras: Vec<Option<Arc<Extras>>>,
+    ) -> Self {
+        let mut nodes = doc
+            .nodes()
+            .zip(node_extras)
+            .map(|(node, extras)| SceneNode {
+                name: node.name().map(|s| s.to_owned()),
+                parent: None,
+                children: node.children().map(|c| c.index()).collect(),
+                transform: NodeTransform::from_gltf(node.transform()),
+                world_transform: glam::Mat4::IDENTITY,
+                mesh: node.mesh().map(|m| m.index()),
+                skin: node.skin().map(|s| s.index()),
+                camera: node.camera().map(|c| c.index()),
+                light: node.light().map(|l| l.index()),
+                weights: node
+                    .weights()
+                    .or_else(|| node.mesh().and_then(|m| m.weights()))
+                    .map(|w| w.to_vec())
+                    .unwrap_or_default(),
+                instances: Vec::new(),
+                extras,
+            })
+            .collect::<Vec<_>>();
+        for node in doc.nodes() {
+            for child in node.children() {
+                nodes[child.index()].parent = Some(node.index());
+            }
+        }
+        let mut scene_graph = Self {
+            nodes,
+            roots: scene.nodes().map(|n| n.index()).collect(),
+        };
+        scene_graph.update_world_transforms();
+        scene_graph
+    }
+
+    pub fn nodes(&self) -> &[SceneNode] {
+        &self.nodes
+    }
+
+    pub fn node(&self, index: usize) -> &SceneNode {
+        &self.nodes[index]
+    }
+
+    /// Root nodes of the loaded glTF scene.
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// Index of the first node called `name`.
+    pub fn find_by_name(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|n| n.name() == Some(name))
+    }
+
+    /// Nodes of the loaded glTF scene in depth-first order, parents before children.
+    pub fn walk(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = self.roots.iter().rev().copied().collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            order.push(index);
+            stack.extend(self.nodes[index].children.iter().rev());
+        }
+        order
+    }
+
+    /// Flags `nodes` and all their descendants, indexed by node index.
+    pub(crate) fn subtrees(&self, nodes: impl IntoIterator<Item = usize>) -> Vec<bool> {
+        let mut flagged = vec![false; self.nodes.len()];
+        let mut stack = nodes.into_iter().collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            if !flagged[index] {
+                flagged[index] = true;
+                stack.extend(&self.nodes[index].children);
+            }
+        }
+        flagged
+    }
+
+    /// Rebuilds the node to instance mapping.
+    pub(crate) fn link_instances(&mut self, instance_infos: &[crate::InstanceInfo]) {
+        for node in &mut self.nodes {
+            node.instances.clear();
+        }
+        for (instance_index, info) in instance_infos.iter().enumerate() {
+            if let Some(node_index) = info.node_index {
+                self.nodes[node_index].instances.push(instance_index);
+            }
+        }
+    }
+
+    /// Recomputes world transforms after local transforms changed.
+    pub(crate) fn update_world_transforms(&mut self) {
+        for index in self.walk() {
+            let parent_transform = self.nodes[index]
+                .parent
+                .map_or(glam::Mat4::IDENTITY, |p| self.nodes[p].world_transform);
+            let node = &mut self.nodes[index];
+            node.world_transform = parent_transform * node.transform.matrix();
+        }
+    }
+}
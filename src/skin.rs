@@ -0,0 +1,93 @@
+use crate::scene_graph::SceneGraph;
+
+#[derive(Clone, Debug)]
+pub struct Skin {
+    pub name: Option<String>,
+    /// Node indices of the joints, in the order `JOINTS_0` refers to them.
+    pub joints: Vec<usize>,
+    /// One per joint, identity when the skin does not provide them.
+    pub inverse_bind_matrices: Vec<glam::Mat4>,
+    pub skeleton: Option<usize>,
+}
+
+impl Skin {
+    /// Joint matrices for a mesh skinned by the node `mesh_node`.
+    ///
+    /// The mesh node's world transform is divided out, since the TLAS instance
+    /// of that node already applies it.
+    pub fn joint_matrices(&self, scene_graph: &SceneGraph, mesh_node: usize) -> Vec<glam::Mat4> {
+        let inverse_mesh_transform = scene_graph.node(mesh_node).world_transform().inverse();
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(joint, inverse_bind_matrix)| {
+                inverse_mesh_transform
+                    * scene_graph.node(*joint).world_transform()
+                    * *inverse_bind_matrix
+            })
+            .collect()
+    }
+}
+
+/// Reference implementation of linear blend skinning.
+pub fn skin_positions(
+    positions: &[[f32; 3]],
+    joints: &[[u32; 4]],
+    weights: &[[f32; 4]],
+    joint_matrices: &[glam::Mat4],
+) -> Vec<[f32; 3]> {
+    positions
+        .iter()
+        .zip(joints)
+        .zip(weights)
+        .map(|((position, joints), weights)| {
+            let skin_matrix = joints
+                .iter()
+                .zip(weights)
+                .filter(|(_, weight)| **weight != 0.0)
+                .fold(glam::Mat4::ZERO, |matrix, (joint, weight)| {
+                    matrix + joint_matrices[*joint as usize] * *weight
+                });
+            skin_matrix
+                .transform_point3(glam::Vec3::from(*position))
+                .to_array()
+        })
+        .collect()
+}
+
+pub(crate) fn load_skins(
+    gltf_skins: gltf::iter::Skins,
+    buffers: &[gltf::buffer::Data],
+) -> Vec<Skin> {
+    gltf_skins
+        .map(|skin| {
+            let joints = skin.joints().map(|j| j.index()).collect::<Vec<_>>();
+            let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+            let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+                Some(iter) => iter.map(|m| glam::Mat4::from_cols_array_2d(&m)).collect(),
+                None => vec![glam::Mat4::IDENTITY; joints.len()],
+            };
+            Skin {
+                name: skin.name().map(|s| s.to_owned()),
+                joints,
+                inverse_bind_matrices,
+                skeleton: skin.skeleton().map(|n| n.index()),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_skin_positions() {
+    let joint_matrices = [
+        glam::Mat4::IDENTITY,
+        glam::Mat4::from_translation(glam::Vec3::new(0.0, 2.0, 0.0)),
+    ];
+    let skinned = skin_positions(
+        &[[1.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+        &[[0, 1, 0, 0], [1, 0, 0, 0]],
+        &[[0.5, 0.5, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0]],
+        &joint_matrices,
+    );
+    assert_eq!(skinned, vec![[1.0, 1.0, 0.0], [1.0, 2.0, 0.0]]);
+}
@@ -0,0 +1,154 @@
+use crate::{draco, instancing, meshopt, variants, DracoError, MeshoptError};
+
+/// How far the loader implements an extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtensionSupport {
+    Supported,
+    /// Implemented for some of the data the extension allows, loading fails
+    /// with an error on the rest.
+    Partial,
+    /// Not implemented, the data it adds is skipped.
+    Ignored,
+}
+
+impl ExtensionSupport {
+    pub fn of(name: &str) -> Self {
+        match name {
+            "KHR_lights_punctual"
+            | "KHR_mesh_quantization"
+            | meshopt::EXTENSION_NAME
+            | instancing::EXTENSION_NAME
+            | variants::EXTENSION_NAME => ExtensionSupport::Supported,
+            // only streams the built-in decoder reads, see `SequentialDracoDecoder`
+            draco::EXTENSION_NAME => ExtensionSupport::Partial,
+            _ => ExtensionSupport::Ignored,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtensionStatus {
+    pub name: String,
+    /// Listed in `extensionsRequired`.
+    pub required: bool,
+    pub support: ExtensionSupport,
+}
+
+/// The extensions a document uses and how the loader handles each.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SupportReport {
+    pub extensions: Vec<ExtensionStatus>,
+}
+
+impl SupportReport {
+    pub fn new(doc: &gltf::Document) -> Self {
+        let mut names = doc
+            .extensions_used()
+            .chain(doc.extensions_required())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        let extensions = names
+            .into_iter()
+            .map(|name| ExtensionStatus {
+                name: name.to_owned(),
+                required: doc.extensions_required().any(|r| r == name),
+                support: ExtensionSupport::of(name),
+            })
+            .collect();
+        Self { extensions }
+    }
+
+    /// Required extensions the loader ignores. Loading fails when there are
+    /// any, unless [`LoadOptions::best_effort`](crate::LoadOptions) is set.
+    pub fn unsupported_required(&self) -> impl Iterator<Item = &ExtensionStatus> {
+        self.extensions
+            .iter()
+            .filter(|e| e.required && e.support == ExtensionSupport::Ignored)
+    }
+}
+
+/// Why [`Scene::from_file_with_options`](crate::Scene::from_file_with_options) failed.
+#[derive(Debug)]
+pub enum LoadError {
+    Gltf(gltf::Error),
+    Meshopt(MeshoptError),
+    Draco(DracoError),
+    /// Names of required extensions the loader ignores, see [`SupportReport`].
+    UnsupportedExtensions(Vec<String>),
+    /// The document does not name a default scene.
+    NoDefaultScene,
+    /// The scene's instances have more geometries than 24-bit instance custom
+    /// indices can address, see [`MAX_CUSTOM_INDEX`](crate::MAX_CUSTOM_INDEX).
+    TooManyGeometries,
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Gltf(error) => write!(f, "{}", error),
+            LoadError::Meshopt(error) => write!(f, "{}", error),
+            LoadError::Draco(error) => write!(f, "{}", error),
+            LoadError::UnsupportedExtensions(names) => {
+                write!(f, "unsupported required extensions: {}", names.join(", "))
+            }
+            LoadError::NoDefaultScene => write!(f, "no default scene"),
+            LoadError::TooManyGeometries => {
+                write!(
+                    f,
+                    "instance custom indices exceed {}",
+                    crate::MAX_CUSTOM_INDEX
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Gltf(error) => Some(error),
+            LoadError::Meshopt(error) => Some(error),
+            LoadError::Draco(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for LoadError {
+    fn from(error: gltf::Error) -> Self {
+        LoadError::Gltf(error)
+    }
+}
+
+impl From<MeshoptError> for LoadError {
+    fn from(error: MeshoptError) -> Self {
+        LoadError::Meshopt(error)
+    }
+}
+
+impl From<DracoError> for LoadError {
+    fn from(error: DracoError) -> Self {
+        LoadError::Draco(error)
+    }
+}
+
+#[test]
+fn test_support_report() {
+    let json = r#"{
+        "asset": {"version": "2.0"},
+        "extensionsUsed": ["KHR_texture_transform", "KHR_draco_mesh_compression"],
+        "extensionsRequired": ["KHR_draco_mesh_compression", "KHR_texture_transform"]
+    }"#;
+    let doc = gltf::Gltf::from_slice(json.as_bytes()).unwrap().document;
+    let report = SupportReport::new(&doc);
+    assert_eq!(report.extensions.len(), 2);
+    assert_eq!(report.extensions[0].support, ExtensionSupport::Partial);
+    assert_eq!(
+        report
+            .unsupported_required()
+            .map(|e| e.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["KHR_texture_transform"]
+    );
+}
@@ -6,32 +6,96 @@ pub fn convert_image_to_bgra8(
     image: &gltf::image::Data,
 ) -> image::ImageBuffer<image::Bgra<u8>, Vec<u8>> {
     use image::DynamicImage;
-    let bgra8;
-
     match image.format {
         gltf::image::Format::R8G8B8 => {
             let img =
                 image::RgbImage::from_vec(image.width, image.height, image.pixels.clone()).unwrap();
-            bgra8 = DynamicImage::ImageRgb8(img).into_bgra8();
+            DynamicImage::ImageRgb8(img).into_bgra8()
         }
         gltf::image::Format::R8G8B8A8 => {
             let img = image::ImageBuffer::from_vec(image.width, image.height, image.pixels.clone())
                 .unwrap();
-            bgra8 = DynamicImage::ImageRgba8(img).into_bgra8();
+            DynamicImage::ImageRgba8(img).into_bgra8()
         }
         gltf::image::Format::B8G8R8 => {
             let img = image::ImageBuffer::from_vec(image.width, image.height, image.pixels.clone())
                 .unwrap();
-            bgra8 = DynamicImage::ImageBgr8(img).into_bgra8();
+            DynamicImage::ImageBgr8(img).into_bgra8()
         }
         gltf::image::Format::B8G8R8A8 => {
             let img = image::ImageBuffer::from_vec(image.width, image.height, image.pixels.clone())
                 .unwrap();
-            bgra8 = DynamicImage::ImageBgra8(img).into_bgra8();
+            DynamicImage::ImageBgra8(img).into_bgra8()
+        }
+        gltf::image::Format::R8 => {
+            let img = image::ImageBuffer::from_vec(image.width, image.height, image.pixels.clone())
+                .unwrap();
+            DynamicImage::ImageLuma8(img).into_bgra8()
+        }
+        gltf::image::Format::R8G8 => {
+            let img = image::ImageBuffer::from_vec(image.width, image.height, image.pixels.clone())
+                .unwrap();
+            DynamicImage::ImageLumaA8(img).into_bgra8()
+        }
+        gltf::image::Format::R16 => {
+            let img =
+                image::ImageBuffer::from_vec(image.width, image.height, u16_pixels(image)).unwrap();
+            DynamicImage::ImageLuma16(img).into_bgra8()
         }
-        _ => {
-            unimplemented!()
+        gltf::image::Format::R16G16 => {
+            let img =
+                image::ImageBuffer::from_vec(image.width, image.height, u16_pixels(image)).unwrap();
+            DynamicImage::ImageLumaA16(img).into_bgra8()
+        }
+        gltf::image::Format::R16G16B16 => {
+            let img =
+                image::ImageBuffer::from_vec(image.width, image.height, u16_pixels(image)).unwrap();
+            DynamicImage::ImageRgb16(img).into_bgra8()
         }
+        gltf::image::Format::R16G16B16A16 => {
+            let img =
+                image::ImageBuffer::from_vec(image.width, image.height, u16_pixels(image)).unwrap();
+            DynamicImage::ImageRgba16(img).into_bgra8()
+        }
+    }
+}
+
+/// 16 bit channels, stored in native byte order like `DynamicImage::to_bytes`.
+fn u16_pixels(image: &gltf::image::Data) -> Vec<u16> {
+    image
+        .pixels
+        .chunks_exact(2)
+        .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+        .collect()
+}
+
+pub fn convert_image_to_rgba8(image: &gltf::image::Data) -> image::RgbaImage {
+    use image::buffer::ConvertBuffer;
+    convert_image_to_bgra8(image).convert()
+}
+
+#[test]
+fn test_convert_16_bit_image() {
+    let pixels = [0xffffu16, 0x8000, 0x0000, 0xffff];
+    let image = gltf::image::Data {
+        pixels: pixels.iter().flat_map(|p| p.to_ne_bytes()).collect(),
+        format: gltf::image::Format::R16G16B16A16,
+        width: 1,
+        height: 1,
+    };
+    assert_eq!(convert_image_to_rgba8(&image).as_raw(), &[255, 128, 0, 255]);
+}
+
+#[test]
+fn test_convert_luma_alpha_image() {
+    let image = gltf::image::Data {
+        pixels: vec![200, 100],
+        format: gltf::image::Format::R8G8,
+        width: 1,
+        height: 1,
     };
-    bgra8
+    assert_eq!(
+        convert_image_to_rgba8(&image).as_raw(),
+        &[200, 200, 200, 100]
+    );
 }
@@ -0,0 +1,105 @@
+use crate::raw_json::RawJson;
+
+pub(crate) const EXTENSION_NAME: &str = "KHR_materials_variants";
+
+/// Variants declared by `KHR_materials_variants` and the material each
+/// primitive uses in them. Material indices follow
+/// [`PrimitiveInfo::material_index`](crate::PrimitiveInfo), 0 being the
+/// default material.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MaterialVariants {
+    pub names: Vec<String>,
+    pub active: Option<usize>,
+    /// Material of every primitive without a variant, indexed by mesh then
+    /// primitive.
+    default_materials: Vec<Vec<u64>>,
+    /// `(variant, material)` pairs of every primitive, indexed like
+    /// `default_materials`.
+    mappings: Vec<Vec<Vec<(usize, u64)>>>,
+}
+
+fn material_index(material: Option<usize>) -> u64 {
+    match material {
+        Some(i) => i as u64 + 1,
+        None => 0,
+    }
+}
+
+impl MaterialVariants {
+    pub fn load(doc: &gltf::Document, raw_json: &RawJson) -> Self {
+        let names = raw_json
+            .root_extension(EXTENSION_NAME)
+            .and_then(|e| e.get("variants")?.as_array())
+            .map(|variants| {
+                variants
+                    .iter()
+                    .map(|v| {
+                        v.get("name")
+                            .and_then(|n| n.as_str())
+                            .unwrap_or_default()
+                            .to_owned()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let default_materials = doc
+            .meshes()
+            .map(|mesh| {
+                mesh.primitives()
+                    .map(|p| material_index(p.material().index()))
+                    .collect()
+            })
+            .collect();
+        let mappings = doc
+            .meshes()
+            .map(|mesh| {
+                mesh.primitives()
+                    .map(|primitive| {
+                        let mappings = raw_json
+                            .primitive_extension(mesh.index(), primitive.index(), EXTENSION_NAME)
+                            .and_then(|e| e.get("mappings")?.as_array());
+                        let mut pairs = Vec::new();
+                        for mapping in mappings.into_iter().flatten() {
+                            let material = mapping.get("material").and_then(|m| m.as_u64());
+                            let variants = mapping.get("variants").and_then(|v| v.as_array());
+                            if let (Some(material), Some(variants)) = (material, variants) {
+                                pairs.extend(variants.iter().filter_map(|v| {
+                                    Some((
+                                        v.as_u64()? as usize,
+                                        material_index(Some(material as usize)),
+                                    ))
+                                }));
+                            }
+                        }
+                        pairs
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            names,
+            active: None,
+            default_materials,
+            mappings,
+        }
+    }
+
+    /// Material of a primitive in `variant`, its default material when the
+    /// variant does not map it or `variant` is `None`.
+    pub fn material_index(
+        &self,
+        mesh_index: usize,
+        primitive_index: usize,
+        variant: Option<usize>,
+    ) -> u64 {
+        let mapped = variant.and_then(|variant| {
+            self.mappings[mesh_index][primitive_index]
+                .iter()
+                .find(|(v, _)| *v == variant)
+        });
+        match mapped {
+            Some((_, material)) => *material,
+            None => self.default_materials[mesh_index][primitive_index],
+        }
+    }
+}